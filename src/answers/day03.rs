@@ -1,145 +1,32 @@
 use std::{fs::read_to_string, io};
-use crate::scanner::{Action, Scanner};
-
-fn parse_mul(scanner: &mut Scanner) -> Option<i32> {
-    let result = scanner.scan(|symbol| {
-        match symbol {
-            "m" => Some(Action::Require),
-            "mu" => Some(Action::Require),
-            "mul" => Some(Action::Require),
-            "mul(" => Some(Action::Return(true)),
-            _ => None,
-        }
-    });
-
-    if let Ok(option) = result {
-        if option.is_none() {
-            scanner.pop();
-            return None;
-        }
-    } else {
-        return None;
-    }
-
-    let left = scanner.try_i32();
-    if left.is_none() {
-        scanner.pop();
-        return None;
-    }
-    let left = left.unwrap();
-
-    if left > 999 {
-        return None;
-    }
-
-    if !scanner.take(&',') {
-        return None
-    }
-    
-    let right = scanner.try_i32();
-    if right.is_none() {
-        scanner.pop();
-        return None;
-    }
-    let right = right.unwrap();
-
-    if right > 999 {
-        return None;
-    }
-
-    if !scanner.take(&')') {
-        return None
-    }
+use crate::interpreter::Interpreter;
+
+/// Multiplies pairs of one to three digit numbers, like the puzzle asks for.
+fn multiplier() -> Interpreter<i32> {
+    let mut interpreter = Interpreter::new(0);
+    interpreter
+        .args(|s| s.try_digits(1..=3))
+        .on("mul", |sum, args| {
+            if let [left, right] = *args {
+                *sum += (left * right) as i32;
+            }
+        });
 
-    Some(left * right)
+    interpreter
 }
 
 fn part1(text: &str) -> i32 {
-    let result: i32 = text.lines()
-        .map(|line| {
-            let mut scanner = Scanner::new(line);
-            let mut sum = 0;
-            while !scanner.is_done() {
-                if let Some(num) = parse_mul(&mut scanner) {
-                    sum += num;
-                }
-            }
-            
-            sum
-        })
-        .sum();
+    let result = *multiplier().run(text);
 
     println!("part1 result = {result}");
 
     result
 }
 
-fn parse_do(scanner: &mut Scanner) {
-    if let Some(char) = scanner.peek() {
-        if *char != 'd' {
-            scanner.pop();
-            return;
-        }
-    } else {
-        return;
-    }
-
-    let result = scanner.scan(|symbol| {
-        match symbol {
-            "d" => Some(Action::Require),
-            "do" => Some(Action::Require),
-            "do(" => Some(Action::Require),
-            "do()" => Some(Action::Return(true)),
-            _ => None,
-        }
-    });
-
-    if let Ok(option) = result {
-        if option.is_some() {
-            scanner.r#do();
-        }
-    }
-}
-
-fn parse_dont(scanner: &mut Scanner) -> bool {
-    let result = scanner.scan(|symbol| {
-        match symbol {
-            "d" => Some(Action::Require),
-            "do" => Some(Action::Require),
-            "don" => Some(Action::Require),
-            "don'" => Some(Action::Require),
-            "don't" => Some(Action::Require),
-            "don't(" => Some(Action::Require),
-            "don't()" => Some(Action::Return(true)),
-            _ => None,
-        }
-    });
-
-    if let Ok(option) = result {
-        if option.is_some() {
-            scanner.dont();
-            return true;
-        } 
-    }
-
-    false
-}
-
 fn part2(text: &str) -> i32 {
-    let mut scanner = Scanner::new(text);
-    let mut result: i32 = 0;
-
-    while !scanner.is_done() {
-        if scanner.enabled() {
-            if !parse_dont(&mut scanner) {
-                if let Some(num) = parse_mul(&mut scanner) {
-                    result += num;
-                }
-            }
-        } else {
-            parse_do(&mut scanner);
-        }
-    }
+    let mut interpreter = multiplier();
+    interpreter.toggles("do()", "don't()");
+    let result = *interpreter.run(text);
 
     println!("part2 result = {result}");
 
@@ -160,3 +47,47 @@ pub fn answer() -> Result<(), io::Error> {
     Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part1() {
+        let text = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+
+        assert_eq!(part1(text), 161);
+    }
+
+    #[test]
+    fn test_mul_inside_broken_mul() {
+        assert_eq!(part1("mul(12,mul(2,3))"), 6);
+        assert_eq!(part1("mumul(2,3)"), 6);
+    }
+
+    #[test]
+    fn test_no_sign_or_long_numbers() {
+        assert_eq!(part1("mul(+2,4)mul(3,4)"), 12);
+        assert_eq!(part1("mul(1234,5)mul(123,5)"), 615);
+    }
+
+    #[test]
+    fn test_malformed_muls() {
+        let malformed = [
+            "mul(-2,4)", "mul(2,+4)", "mul(2,1234)", "mul(0002,4)",
+            "mul( 2,4)", "mul(2 ,4)", "mul(2, 4)", "mul(2,4 )",
+            "mul(2,4", "mul(,4)", "mul(2)", "mul(2,4,6)", "MUL(2,4)",
+        ];
+
+        for text in malformed {
+            assert_eq!(part1(text), 0, "{text} isn't a valid mul");
+        }
+    }
+
+    #[test]
+    fn test_part2() {
+        let text = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
+        assert_eq!(part2(text), 48);
+    }
+}
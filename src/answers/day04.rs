@@ -1,71 +1,43 @@
 use std::{fs::read_to_string, io};
+use crate::{matrix::{Matrix, Mask}, position::Pos};
 
 // Thanks to capito27 for the better way of masking the input
 
-struct Mask {
-    relative_pos: Vec<isize>,
-    left_offset: isize,
-    right_offset: isize,
+fn parse(contents: &str) -> Matrix<char> {
+    let width = contents.find("\n").unwrap();
+    let rows = contents.replace("\n", "").chars().collect();
+
+    Matrix::new(rows, width)
 }
 
-impl Mask {
-    fn new(relative_pos: Vec<isize>, left_offset: isize, right_offset: isize) -> Self {
-        Self {
-            relative_pos,
-            left_offset,
-            right_offset,
-        }
-    }
-
-    fn apply<'a, T>(&self, pos: isize, width: isize, vals: &'a [T]) -> Option<Vec<&'a T>> {
-        if pos % width < self.left_offset || (pos % width) + self.right_offset >= width {
-            return None;
-        }
-
-        self.relative_pos
-            .iter()
-            .map(|&i| vals.get((i+pos) as usize))
-            .collect::<Option<Vec<_>>>()
-    }
+/// Every mask either fits entirely inside the matrix or gets discarded.
+fn apply_whole<'a>(mask: &Mask, pos: Pos, matrix: &'a Matrix<char>) -> Option<Vec<&'a char>> {
+    mask.apply(pos, matrix)
+        .into_iter()
+        .collect()
 }
 
-/// Because the input is one string, we can move between rows by adding
-/// the width to the relative position.
-fn xmas_masks(w: isize) -> Vec<Mask> {
-    vec![
-        // ->
-        Mask::new(vec![0, 1, 2, 3], 0, 3),
-        // diag_right_down
-        Mask::new(vec![0, w+1, 2*w+2, 3*w+3], 0, 3),
-        // down
-        Mask::new(vec![0, w, 2*w, 3*w], 0, 0),
-        // diag_left_down
-        Mask::new(vec![0, w-1, 2*w-2, 3*w-3], 3, 0),
-        // <-
-        Mask::new(vec![0, -1, -2, -3], 3, 0),
-        // diag_left_up
-        Mask::new(vec![0, -w-1, -2*w-2, -3*w-3], 3, 0),
-        // up
-        Mask::new(vec![0, -w, -2*w, -3*w], 0, 0),
-        // diag_right_up
-        Mask::new(vec![0, -w+1, -2*w+2, -3*w+3], 0, 3),
-    ]
+fn xmas_masks() -> Vec<Mask> {
+    // ->, diag_right_down, down, diag_left_down,
+    // <-, diag_left_up, up, diag_right_up
+    [Pos::new(0,1), Pos::new(1,1), Pos::new(1,0), Pos::new(1,-1), Pos::new(0,-1), Pos::new(-1,-1), Pos::new(-1,0), Pos::new(-1,1)]
+        .into_iter()
+        .map(|dir| Mask::ray(dir, 4))
+        .collect()
 }
 
 fn part1() -> Result<(), io::Error> {
     let contents = read_to_string("input/day04.txt")?;
+    let matrix = parse(&contents);
 
-    let width = contents.find("\n").unwrap() as isize;
-    let contents = contents.replace("\n", "").chars().collect::<Vec<char>>();
-    let contents_slice = contents.as_slice();
+    let masks = xmas_masks();
 
-    let masks = xmas_masks(width);
-
-    let result = (0..contents.len() as isize)
-        .flat_map(|pos| {
+    let result = matrix.give_pos()
+        .flat_map(|(_val, pos)| {
             masks
                 .iter()
-                .filter_map(move |mask| mask.apply(pos, width, contents_slice))
+                .filter_map(|mask| apply_whole(mask, pos, &matrix))
+                .collect::<Vec<_>>()
         })
         .filter(|vals| {
             vals.len() == 4 && parse_xmas(vals)
@@ -82,24 +54,24 @@ fn parse_xmas(text: &[&char]) -> bool {
 }
 
 /// Valid masks should equal MSAMS
-fn x_mas_masks(w: isize) -> Vec<Mask>{
+fn x_mas_masks() -> Vec<Mask>{
     vec![
         // M.S
         // .A.
         // M.S
-        Mask::new(vec![0, 2, w+1, 2*w, 2*w+2], 0, 2),
+        Mask::new(vec![Pos::new(0,0), Pos::new(0,2), Pos::new(1,1), Pos::new(2,0), Pos::new(2,2)]),
         // S.S
         // .A.
         // M.M
-        Mask::new(vec![2*w, 0, w+1, 2*w+2, 2], 0, 2),
+        Mask::new(vec![Pos::new(2,0), Pos::new(0,0), Pos::new(1,1), Pos::new(2,2), Pos::new(0,2)]),
         // M.M
         // .A.
         // S.S
-        Mask::new(vec![0, 2*w, w+1, 2, 2*w+2], 0, 2),
+        Mask::new(vec![Pos::new(0,0), Pos::new(2,0), Pos::new(1,1), Pos::new(0,2), Pos::new(2,2)]),
         // S.M
         // .A.
         // S.M
-        Mask::new(vec![2, 0, w+1, 2*w+2, 2*w], 0, 2),
+        Mask::new(vec![Pos::new(0,2), Pos::new(0,0), Pos::new(1,1), Pos::new(2,2), Pos::new(2,0)]),
     ]
 }
 
@@ -109,18 +81,16 @@ fn parse_x_mas(text: &[&char]) -> bool {
 
 fn part2() -> Result<(), io::Error> {
     let contents = read_to_string("input/day04.txt")?;
+    let matrix = parse(&contents);
 
-    let width = contents.find("\n").unwrap() as isize;
-    let contents = contents.replace("\n", "").chars().collect::<Vec<char>>();
-    let contents_slice = contents.as_slice();
-
-    let masks = x_mas_masks(width);
+    let masks = x_mas_masks();
 
-    let result = (0..contents.len() as isize)
-        .flat_map(|pos| {
+    let result = matrix.give_pos()
+        .flat_map(|(_val, pos)| {
             masks
                 .iter()
-                .filter_map(move |mask| mask.apply(pos, width, contents_slice))
+                .filter_map(|mask| apply_whole(mask, pos, &matrix))
+                .collect::<Vec<_>>()
         })
         .filter(|vals| {
             vals.len() == 5 && parse_x_mas(vals)
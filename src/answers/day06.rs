@@ -1,212 +1,158 @@
-use std::{fs::read_to_string, io, collections::HashSet};
+use std::{fs::read_to_string, io};
+use crate::{bit_matrix::BitMatrix3, matrix::Matrix, position::{Dir, Pos}, thread_pool::ThreadPool, viz::{self, Player}};
 
-struct Mask {
-    relative_pos: Vec<isize>,
-    left_offset: isize,
-    right_offset: isize,
-}
-
-impl Mask {
-    fn new(relative_pos: Vec<isize>, left_offset: isize, right_offset: isize) -> Self {
-        Self {
-            relative_pos,
-            left_offset,
-            right_offset,
-        }
-    }
-
-    fn apply<'a, T>(&self, pos: isize, width: isize, vals: &'a [T]) -> Option<Vec<&'a T>> {
-        if pos % width < self.left_offset || (pos % width) + self.right_offset >= width {
-            return None;
-        }
-
-        self.relative_pos
-            .iter()
-            .map(|&i| vals.get((i+pos) as usize))
-            .collect::<Option<Vec<_>>>()
-    }
-}
-
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
-enum Dirs {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Dirs {
-    fn rotate(&mut self) {
-        match self {
-            Dirs::Right => *self = Dirs::Down,
-            Dirs::Left => *self = Dirs::Up,
-            Dirs::Down => *self = Dirs::Left,
-            Dirs::Up => *self = Dirs::Right,
-        }
-    }
-}
+fn parse(contents: &str) -> Matrix<char> {
+    let width = contents.find("\n").unwrap();
+    let rows = contents.replace("\n", "").chars().collect();
 
-/// Because the input is one string, we can move between rows by adding
-/// the width to the relative position.
-fn direction_masks(w: isize) -> Vec<Mask> {
-    // ...
-    // .^.
-    // ...
-    vec![
-        Mask::new(vec![-w], 0, 0),
-        Mask::new(vec![w], 0, 0),
-        Mask::new(vec![-1], 1, 0),
-        Mask::new(vec![1], 0, 1),
-    ]
+    Matrix::new(rows, width)
 }
 
 fn part1() -> Result<(), io::Error>{
     let contents = read_to_string("input/day06.txt")?;
-
-    let width = contents.find("\n").unwrap() as isize;
-    let mut contents = contents.replace("\n", "").chars().collect::<Vec<char>>();
+    let mut contents = parse(&contents);
 
     let pos = find_guard(&contents);
 
-    let masks = direction_masks(width);
-    let mut initial_dir = Dirs::Up;
+    let mut initial_dir = Dir::Up;
 
-    let result = simulate_path(pos, width, &mut contents, &mut initial_dir, &masks);
+    let result = simulate_path(pos, &mut contents, &mut initial_dir);
     println!("result = {result}");
 
     Ok(())
 }
 
-fn find_guard(content: &[char]) -> isize {
-    let mut pos = 0;
-    for char in content {
-        if *char == '^' {
-            break;
-        }
-        pos += 1;
-    }
-    pos
-}
-
-fn get_mask<'a>(masks: &'a [Mask], dir: &Dirs) -> &'a Mask {
-    match dir {
-        Dirs::Up => &masks[0],
-        Dirs::Down => &masks[1],
-        Dirs::Left => &masks[2],
-        Dirs::Right => &masks[3],
-    }
+fn find_guard(content: &Matrix<char>) -> Pos {
+    content.give_pos()
+        .find(|(char, _pos)| **char == '^')
+        .map(|(_char, pos)| pos)
+        .unwrap()
 }
 
-fn simulate_path(pos: isize, w: isize, content: &mut [char], dir: &mut Dirs, masks: &[Mask]) -> i32 {
+fn simulate_path(pos: Pos, content: &mut Matrix<char>, dir: &mut Dir) -> i32 {
     let mut pos = pos;
     let mut res = 0;
 
-    let mut mask = get_mask(masks, dir);
-
     loop {
-        let curr_pos = content[pos as usize];
-        if curr_pos != 'X' {
-            res += 1;
-            content[pos as usize] = 'X';
-        }
-        let look_ahead = mask.apply(pos, w, content);
-        if let Some(char) = look_ahead {
-            match char.first().unwrap() {
-                '#' => { 
-                    dir.rotate();
-                    mask = get_mask(masks, dir);
-                },
-                _ => { pos = advance(pos, dir, w); },
+        let (walked, obstacle) = content.cast(pos, *dir, |c| *c == '#');
+        for p in &walked {
+            if content[*p] != 'X' {
+                res += 1;
+                content[*p] = 'X';
             }
-        } else {
-            break;
+        }
+        pos = *walked.last().unwrap();
+
+        match obstacle {
+            Some(_) => *dir = dir.turn_right(),
+            None => break,
         }
     }
 
     res
 }
 
-fn advance(pos: isize, dir: &Dirs, w: isize) -> isize {
-    match dir {
-        Dirs::Up => pos-w,
-        Dirs::Down => pos+w,
-        Dirs::Left => pos-1,
-        Dirs::Right => pos+1,
-    }
-}
-
 fn part2() -> Result<(), io::Error>{
     let contents = read_to_string("input/day06.txt")?;
-
-    let width = contents.find("\n").unwrap() as isize;
-    let mut contents = contents.replace("\n", "").chars().collect::<Vec<char>>();
+    let contents = parse(&contents);
 
     let initial_pos = find_guard(&contents);
 
-    let masks = direction_masks(width);
-    let mut initial_dir = Dirs::Up;
+    let mut initial_dir = Dir::Up;
 
     let mut path_result = contents.clone();
-    let _result = simulate_path(initial_pos, width, &mut path_result, &mut initial_dir, &masks);
+    let _result = simulate_path(initial_pos, &mut path_result, &mut initial_dir);
 
-    let mut result = 0;
-    
-    for pos in 0..initial_pos {
-        if path_result[pos as usize] == 'X' {
+    let blocks: Vec<Pos> = path_result.give_pos()
+        .filter(|(val, pos)| **val == 'X' && *pos != initial_pos)
+        .map(|(_, pos)| pos)
+        .collect();
 
-            result += check_loop(initial_pos, pos, width, &mut contents, &masks)
-        }
-    }
-    for pos in initial_pos+1..path_result.len() as isize {
-        if path_result[pos as usize] == 'X' {
+    let pool: ThreadPool = ThreadPool::default();
 
-            result += check_loop(initial_pos, pos, width, &mut contents, &masks)
-        }
-    }
+    // Every worker only needs its own visited set, the grid is shared.
+    let chunk_size = blocks.len().div_ceil(pool.size() * 4).max(1);
+    let result: i32 = pool
+        .par_chunks(&blocks, chunk_size, |_, chunk| {
+            let mut visited = BitMatrix3::new(contents.row_count(), contents.width(), 4);
+
+            chunk.iter()
+                .map(|block_pos| check_loop(initial_pos, *block_pos, &contents, &mut visited))
+                .sum::<i32>()
+        })
+        .expect("check_loop shouldn't panic")
+        .into_iter()
+        .sum();
 
     println!("result = {result}");
     
     Ok(())
 }
 
-fn check_loop(initial_pos: isize, block_pos: isize, w: isize, content: &mut [char], masks: &[Mask]) -> i32{
+fn check_loop(
+    initial_pos: Pos,
+    block_pos: Pos,
+    content: &Matrix<char>,
+    visited: &mut BitMatrix3,
+) -> i32{
     let mut counts = 0;
-    let mut dir = Dirs::Up;
+    let mut dir = Dir::Up;
 
     let mut pos = initial_pos;
 
-    let mut mask = get_mask(masks, &dir);
+    visited.clear_all();
 
-    let mut visited = HashSet::new();
+    // A loop means hitting the same obstacle from the same side twice,
+    // so only the turns need to be remembered.
+    loop {
+        let (mut walked, mut obstacle) = content.cast(pos, dir, |c| *c == '#');
 
-    let save_state = content[block_pos as usize];
-    content[block_pos as usize] = 'O';
+        // The grid isn't touched, so stop in front of the new block by hand.
+        if let Some(index) = walked.iter().position(|walked_pos| *walked_pos == block_pos) {
+            walked.truncate(index);
+            obstacle = Some(block_pos);
+        }
+        pos = *walked.last().unwrap();
 
-    loop {
-        let looped = !visited.insert((pos, dir.clone()));
-        if looped {
-            counts = 1;
+        if obstacle.is_none() {
             break;
         }
-        let look_ahead = mask.apply(pos, w, content);
-        if let Some(char) = look_ahead {
-            match char.first().unwrap() {
-                '#' | 'O' => { 
-                    dir.rotate();
-                    mask = get_mask(masks, &dir);
-                },
-                _ => { pos = advance(pos, &dir, w); },
-            }
-        } else {
+        if !visited.insert_pos(&pos, dir.index()) {
+            counts = 1;
             break;
         }
+        dir = dir.turn_right();
     }
 
-    content[block_pos as usize] = save_state;
-
     counts
 }
 
+/// Same walk as part1, one step per frame.
+fn visualize() -> Result<(), io::Error> {
+    let contents = read_to_string("input/day06.txt")?;
+    let contents = parse(&contents);
+
+    let mut pos = find_guard(&contents);
+    let mut dir = Dir::Up;
+
+    let mut player = Player::from_fn(contents, |frame| {
+        let ahead = *frame.get_pos(&(pos + dir))?;
+
+        let mut frame = frame.clone();
+        if ahead == '#' {
+            dir = dir.turn_right();
+        } else {
+            frame[pos] = 'X';
+            pos = pos + dir;
+        }
+        frame[pos] = dir.to_char();
+
+        Some(frame)
+    });
+
+    player.run()
+}
+
 pub fn answer() -> Result<(), io::Error>{
     println!("Part1:");
     part1()?;
@@ -214,5 +160,9 @@ pub fn answer() -> Result<(), io::Error>{
     println!("Part2:");
     part2()?;
 
+    if viz::enabled() {
+        visualize()?;
+    }
+
     Ok(())
 }
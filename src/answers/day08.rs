@@ -29,7 +29,7 @@ fn find_antinodes(matrix: Matrix<char>) -> i32 {
 
     let mut ret_matrix = Matrix::with_capacity(matrix.row_count(), matrix.width(), '.'); 
 
-    let mut pos = Pos(0, 0);
+    let mut pos = Pos::new(0, 0);
     for mat_entry in &matrix {
         let vec_ref = char_positions.entry(*mat_entry).or_default();
         vec_ref.push(pos);
@@ -93,7 +93,7 @@ fn find_antinodes_harmonics(matrix: Matrix<char>) -> i32 {
 
     let mut ret_matrix = Matrix::with_capacity(matrix.row_count(), matrix.width(), '.');
 
-    let mut pos = Pos(0, 0);
+    let mut pos = Pos::new(0, 0);
     for mat_entry in &matrix {
         let vec_ref = char_positions.entry(*mat_entry).or_default();
         vec_ref.push(pos);
@@ -114,15 +114,14 @@ fn find_antinodes_harmonics(matrix: Matrix<char>) -> i32 {
                 for end in positions.iter().skip(i+1) {
                     let offset = *end - start;
 
-                    let mut times = 0;
-                    while let Some(entry) = ret_matrix.get_mut_pos(&(start+(offset*times))) {
-                        *entry = '$';
-                        times += 1;
+                    let forward = start.ray(offset);
+                    let backward = start.ray(-offset).skip(1);
+
+                    for pos in forward.take_while(|pos| matrix.get_pos(pos).is_some()) {
+                        ret_matrix[pos] = '$';
                     }
-                    let mut times = 1;
-                    while let Some(entry) = ret_matrix.get_mut_pos(&(start-(offset*times))) {
-                        *entry = '$';
-                        times += 1;
+                    for pos in backward.take_while(|pos| matrix.get_pos(pos).is_some()) {
+                        ret_matrix[pos] = '$';
                     }
                 }
             }
@@ -1,5 +1,6 @@
-use std::{collections::{HashSet, VecDeque}, fs::read_to_string, io, ops::Add};
+use std::{collections::{HashSet, VecDeque}, fs::read_to_string, io};
 use crate::{
+    bit_matrix::BitMatrix,
     matrix::{Matrix, Mask},
     position::Pos,
 };
@@ -30,9 +31,9 @@ fn part1(contents: &str) -> i32 {
 }
 
 fn find_score(matrix: &Matrix<char>, pos: Pos) -> i32 {
-    let masks = create_masks();
+    let mask = Mask::cardinal();
 
-    let mut trailends: HashSet<Pos> = HashSet::new();
+    let mut trailends = BitMatrix::new(matrix.row_count(), matrix.width());
     let mut candidates: VecDeque<Pos> = VecDeque::new();
     candidates.push_back(pos);
 
@@ -40,70 +41,20 @@ fn find_score(matrix: &Matrix<char>, pos: Pos) -> i32 {
         let pos = candidates.pop_front().unwrap();
         let num_pos = matrix.get_pos(&pos).unwrap().to_digit(10).unwrap();
 
-        for (i, mask) in masks.iter().enumerate() {
-            let values = mask.apply(pos, matrix);
-            if let Some(Some(val)) = values.first() {
-                let num_val = (**val).to_digit(10).unwrap();
-
-                if num_val > num_pos && num_val.abs_diff(num_pos) == 1 {
-                    let val_pos = pos + Dir::match_num(i);
-                    if num_val == 9 {
-                        trailends.insert(val_pos);
-                    } else {
-                        candidates.push_back(val_pos);
-                    }
+        for (val_pos, val) in mask.apply_with_pos(pos, matrix) {
+            let num_val = val.to_digit(10).unwrap();
+
+            if num_val > num_pos && num_val.abs_diff(num_pos) == 1 {
+                if num_val == 9 {
+                    trailends.insert_pos(&val_pos);
+                } else {
+                    candidates.push_back(val_pos);
                 }
             }
         }
     }
 
-    trailends.len() as i32
-}
-
-enum Dir {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Dir {
-    fn match_num(num: usize) -> Dir {
-        assert!((0..=3).contains(&num));
-        match num {
-            0 => Dir::Up,
-            1 => Dir::Down,
-            2 => Dir::Left,
-            3 => Dir::Right,
-            _ => unreachable!("num restricted to range 0..=3"),
-        }
-    }
-}
-
-impl Add<Dir> for Pos {
-    type Output = Pos;
-
-    fn add(self, rhs: Dir) -> Self::Output {
-        match rhs {
-            Dir::Up => self + Pos(-1,0),
-            Dir::Down => self + Pos(1,0),
-            Dir::Left => self + Pos(0,-1),
-            Dir::Right => self + Pos(0,1),
-        }
-    }
-}
-
-fn create_masks() -> Vec<Mask> {
-    vec![
-        // up
-        Mask::new(vec![Pos(-1, 0)]),
-        // down
-        Mask::new(vec![Pos(1, 0)]),
-        // left
-        Mask::new(vec![Pos(0, -1)]),
-        // right
-        Mask::new(vec![Pos(0, 1)]),
-    ]
+    trailends.count_ones() as i32
 }
 
 fn part2(contents: &str) -> i32 {
@@ -132,7 +83,7 @@ fn part2(contents: &str) -> i32 {
 }
 
 fn find_rating(matrix: &Matrix<char>, pos: Pos) -> i32 {
-    let masks = create_masks();
+    let mask = Mask::cardinal();
 
     let mut trailends: Vec<Pos> = Vec::new();
     let mut candidates: VecDeque<Pos> = VecDeque::new();
@@ -142,18 +93,14 @@ fn find_rating(matrix: &Matrix<char>, pos: Pos) -> i32 {
         let pos = candidates.pop_front().unwrap();
         let num_pos = matrix.get_pos(&pos).unwrap().to_digit(10).unwrap();
 
-        for (i, mask) in masks.iter().enumerate() {
-            let values = mask.apply(pos, matrix);
-            if let Some(Some(val)) = values.first() {
-                let num_val = (**val).to_digit(10).unwrap();
-
-                if num_val > num_pos && num_val.abs_diff(num_pos) == 1 {
-                    let val_pos = pos + Dir::match_num(i);
-                    if num_val == 9 {
-                        trailends.push(val_pos);
-                    } else {
-                        candidates.push_back(val_pos);
-                    }
+        for (val_pos, val) in mask.apply_with_pos(pos, matrix) {
+            let num_val = val.to_digit(10).unwrap();
+
+            if num_val > num_pos && num_val.abs_diff(num_pos) == 1 {
+                if num_val == 9 {
+                    trailends.push(val_pos);
+                } else {
+                    candidates.push_back(val_pos);
                 }
             }
         }
@@ -1,7 +1,8 @@
 use std::collections::{HashSet, VecDeque};
 use std::{fs::read_to_string, io};
+use crate::bit_matrix::BitMatrix;
 use crate::matrix::{Matrix, Mask};
-use crate::position::Pos;
+use crate::position::{Dir, Pos};
 
 type Price = u64;
 
@@ -52,11 +53,11 @@ pub fn answer() -> Result<(), io::Error>{
 }
 
 fn part1(matrix: &Input) -> Price {
-    let mut visited = Matrix::with_capacity(matrix.row_count(), matrix.width(), false);
+    let mut visited = BitMatrix::new(matrix.row_count(), matrix.width());
 
     let mut regions: Vec<Region> = Vec::new();
     for (val, pos) in matrix.give_pos() {
-        if !visited.get_pos(&pos).unwrap() {
+        if !visited[pos] {
             region_expansion(matrix, &mut visited, &mut regions, val, &pos);
         }
     }
@@ -64,45 +65,32 @@ fn part1(matrix: &Input) -> Price {
     regions.iter().map(|region| region.get_price()).sum()
 }
 
-fn make_mask() -> Mask {
-    // all cardinal directions.
-    // .X.
-    // XPX
-    // .X.
-    Mask::new(vec![Pos(-1,0),Pos(1,0),Pos(0,-1),Pos(0,1),])
-}
-
 fn region_expansion(
     matrix: &Matrix<char>,
-    visited: &mut Matrix<bool>,
+    visited: &mut BitMatrix,
     regions: &mut Vec<Region>,
     val: &char, pos: &Pos
 ) {
-    let cardinal_mask = make_mask();
+    // all cardinal directions.
+    // .X.
+    // XPX
+    // .X.
+    let cardinal_mask = Mask::cardinal();
     let mut region = Region::new(*val);
 
     let mut members: VecDeque<Pos> = VecDeque::new();
     members.push_back(*pos);
     while !members.is_empty() {
         let curr_pos = members.pop_front().unwrap();
-        if !*visited.get_pos(&curr_pos).unwrap() {
-            *visited.get_mut_pos(&curr_pos).unwrap() = true;
+        if visited.insert_pos(&curr_pos) {
             region.area += 1;
             
             let neighbours = cardinal_mask.apply(curr_pos, matrix);
 
-            for (i, val) in neighbours.iter().enumerate() {
+            for (offset, val) in cardinal_mask.relative_pos().iter().zip(neighbours) {
                 if let Some(chr) = val {
-                    if **chr == region.plant {
-                        let offset = match i {
-                            0 => Pos(-1,0),
-                            1 => Pos(1,0),
-                            2 => Pos(0,-1),
-                            3 => Pos(0,1),
-                            _ => unreachable!(),
-                        };
-
-                        members.push_back(curr_pos + offset);
+                    if *chr == region.plant {
+                        members.push_back(curr_pos + *offset);
                     } else {
                         region.perimeter += 1;
                     }
@@ -117,11 +105,11 @@ fn region_expansion(
 }
 
 fn part2(matrix: &Input) -> Price {
-    let mut visited = Matrix::with_capacity(matrix.row_count(), matrix.width(), false);
+    let mut visited = BitMatrix::new(matrix.row_count(), matrix.width());
 
     let mut regions: Vec<Region> = Vec::new();
     for (val, pos) in matrix.give_pos() {
-        if !visited.get_pos(&pos).unwrap() {
+        if !visited[pos] {
             region_expansion_discounted(matrix, &mut visited, &mut regions, val, &pos);
         }
     }
@@ -131,11 +119,15 @@ fn part2(matrix: &Input) -> Price {
 
 fn region_expansion_discounted(
     matrix: &Matrix<char>,
-    visited: &mut Matrix<bool>,
+    visited: &mut BitMatrix,
     regions: &mut Vec<Region>,
     val: &char, pos: &Pos
 ) {
-    let cardinal_mask = make_mask();
+    // all cardinal directions.
+    // .X.
+    // XPX
+    // .X.
+    let cardinal_mask = Mask::cardinal();
     let mut region = Region::new(*val);
     let mut perimeter: HashSet<(Pos, Dir)> = HashSet::new();
 
@@ -143,21 +135,13 @@ fn region_expansion_discounted(
     members.push_back(*pos);
     while !members.is_empty() {
         let curr_pos = members.pop_front().unwrap();
-        if !*visited.get_pos(&curr_pos).unwrap() {
-            *visited.get_mut_pos(&curr_pos).unwrap() = true;
+        if visited.insert_pos(&curr_pos) {
             region.area += 1;
             
             let neighbours = cardinal_mask.apply(curr_pos, matrix);
 
-            for (i, val) in neighbours.iter().enumerate() {
-                let (offset, off_dir) = match i {
-                    0 => (Pos(-1,0), Dir::Up),
-                    1 => (Pos(1,0), Dir::Down),
-                    2 => (Pos(0,-1), Dir::Left),
-                    3 => (Pos(0,1), Dir::Right),
-                    _ => unreachable!(),
-                };
-                let relative_pos = curr_pos + offset;
+            for (val, off_dir) in neighbours.iter().zip(Dir::all()) {
+                let relative_pos = curr_pos + off_dir;
 
                 if let Some(chr) = val {
                     if **chr == region.plant {
@@ -180,8 +164,7 @@ fn region_expansion_discounted(
         let (p1, d1) = &perimeter[i];
 
         for (p2, d2) in perimeter.iter().skip(i) {
-            let Pos(a,b) = *p1 - *p2;
-            if d1 == d2 && a.abs() + b.abs() == 1 {
+            if d1 == d2 && p1.manhattan(p2) == 1 {
                 region.perimeter -= 1;
             }
         }
@@ -190,14 +173,6 @@ fn region_expansion_discounted(
     regions.push(region);
 }
 
-#[derive(PartialEq, Eq, Debug, Hash)]
-enum Dir {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
 
 #[cfg(test)]
 mod tests {
@@ -1,5 +1,5 @@
 use std::{fs::read_to_string, io};
-use crate::{matrix::Matrix, scanner::Scanner, position::Pos};
+use crate::{matrix::Matrix, scanner::{Error, Scanner}, position::Pos};
 
 type Input = Vec<Matrix<i128>>;
 type Tokens = u128;
@@ -27,7 +27,7 @@ fn part2(machines: &Input) -> Tokens {
 pub fn answer() -> Result<(), io::Error>{
     let contents = read_to_string("input/day13.txt")?;
     
-    let matrix = parse(&contents);
+    let matrix = parse(&contents)?;
     
     println!("Part1:");
     let part1_res = part1(&matrix);
@@ -44,12 +44,12 @@ pub fn answer() -> Result<(), io::Error>{
 // ax bx px
 // ay by py
 fn solve_2by2<T: Into<i128> + Copy>(matrix: &Matrix<T>, offset: i128) -> Option<Tokens> {
-    let ax: i128 = matrix[Pos(0,0)].into();
-    let bx: i128 = matrix[Pos(0,1)].into();
-    let px: i128 = matrix[Pos(0,2)].into();
-    let ay: i128 = matrix[Pos(1,0)].into();
-    let by: i128 = matrix[Pos(1,1)].into();
-    let py: i128 = matrix[Pos(1,2)].into();
+    let ax: i128 = matrix[Pos::new(0,0)].into();
+    let bx: i128 = matrix[Pos::new(0,1)].into();
+    let px: i128 = matrix[Pos::new(0,2)].into();
+    let ay: i128 = matrix[Pos::new(1,0)].into();
+    let by: i128 = matrix[Pos::new(1,1)].into();
+    let py: i128 = matrix[Pos::new(1,2)].into();
 
     let px = px + offset;
     let py = py + offset;
@@ -77,61 +77,12 @@ fn solve_2by2<T: Into<i128> + Copy>(matrix: &Matrix<T>, offset: i128) -> Option<
     }
 }
 
-fn parse(contents: &str) -> Input {
-    let mut ret = Input::new();
-    let contents = contents.replace("\n", "");
-    let mut scanner = Scanner::new(&contents);
-    
-    while !scanner.is_done() {
-        while let Some(val) = scanner.pop() {
-            if *val == '+' {
-                break;
-            }
-        }
-        let ax = scanner.try_u32().unwrap() as i128;
-
-        while let Some(val) = scanner.pop() {
-            if *val == '+' {
-                break;
-            }
-        }
-        let ay = scanner.try_u32().unwrap() as i128;
-
-        while let Some(val) = scanner.pop() {
-            if *val == '+' {
-                break;
-            }
-        }
-        let bx = scanner.try_u32().unwrap() as i128;
-
-        while let Some(val) = scanner.pop() {
-            if *val == '+' {
-                break;
-            }
-        }
-        let by = scanner.try_u32().unwrap() as i128;
-
-        while let Some(val) = scanner.pop() {
-            if *val == '=' {
-                break;
-            }
-        }
-        let px = scanner.try_u32().unwrap() as i128;
-
-        while let Some(val) = scanner.pop() {
-            if *val == '=' {
-                break;
-            }
-        }
-        let py = scanner.try_u32().unwrap() as i128;
-
-        let rows = vec![ax,bx,px,ay,by,py];
-        let width = 3;
-        let augmented = Matrix::new(rows, width);
-
-        ret.push(augmented);
-    }
+fn parse(contents: &str) -> Result<Input, Error> {
+    let template = "Button A: X+{}, Y+{}\nButton B: X+{}, Y+{}\nPrize: X={}, Y={}";
+    let machines = Scanner::new(contents).extract_all::<(i128, i128, i128, i128, i128, i128)>(template)?;
 
-    ret
+    Ok(machines.into_iter()
+        .map(|(ax, ay, bx, by, px, py)| Matrix::new(vec![ax,bx,px,ay,by,py], 3))
+        .collect())
 }
 
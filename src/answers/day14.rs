@@ -1,5 +1,5 @@
-use std::{fs::read_to_string, io};
-use crate::{matrix::{Matrix, Mask}, position::{Pos, UPos}, scanner::Scanner};
+use std::{fs::{self, read_to_string}, io};
+use crate::{image::{self, FrameRecorder}, matrix::{Matrix, Mask}, position::{Pos, UPos}, scanner::{Error, Scanner}, viz::{self, Player}};
 
 type Input = Vec<Robot>;
 type SafetyFactor = i32;
@@ -13,7 +13,7 @@ struct Robot {
 pub fn answer() -> Result<(), io::Error>{
     let contents = read_to_string("input/day14.txt")?; // input/dayxx.txt
     
-    let robots = parse(&contents);
+    let robots = parse(&contents)?;
 
     println!("Part1:");
     let part1_res = part1(&robots);
@@ -23,9 +23,65 @@ pub fn answer() -> Result<(), io::Error>{
     let part2_res = part2(&robots);
     println!("result = {part2_res}");
 
+    if image::enabled() {
+        save_tree(&robots, part2_res)?;
+    }
+
+    if viz::enabled() {
+        visualize(&robots)?;
+    }
+
+    Ok(())
+}
+
+/// The tree as a PNG, and the last seconds before it shows up as a GIF.
+/// Only written with `--export`.
+fn save_tree(input: &Input, tree_time: i32) -> Result<(), io::Error> {
+    let palette = |count: &i32| if *count > 0 { [0, 160, 0] } else { [0, 0, 0] };
+
+    let mut recorder = FrameRecorder::new();
+    for sec in (tree_time - 10).max(0)..=tree_time {
+        let mut matrix = Matrix::with_capacity(103, 101, 0);
+        for robot in input {
+            let end_pos = simulate_robot(&matrix, robot, sec);
+            matrix[end_pos] += 1;
+        }
+        recorder.record(&matrix, 4, palette);
+    }
+
+    fs::create_dir_all("target/viz")?;
+    recorder.frames().last().unwrap().save("target/viz/day14_tree.png")?;
+    recorder.save_gif("target/viz/day14.gif", 50)?;
+    println!("saved target/viz/day14_tree.png and target/viz/day14.gif");
+
     Ok(())
 }
 
+/// One second per frame
+fn visualize(input: &Input) -> Result<(), io::Error> {
+    let bounds: Matrix<i32> = Matrix::with_capacity(103, 101, 0);
+    let mut robots = input.clone();
+
+    let draw = |robots: &Input| {
+        let mut frame = Matrix::with_capacity(bounds.row_count(), bounds.width(), ' ');
+        for robot in robots {
+            frame[robot.p] = '#';
+        }
+        frame
+    };
+
+    let initial = draw(&robots);
+    let mut player = Player::from_fn(initial, |_frame| {
+        for robot in &mut robots {
+            robot.p = simulate_robot(&bounds, robot, 1);
+        }
+
+        Some(draw(&robots))
+    });
+
+    player.run()
+}
+
 fn part1(input: &Input) -> SafetyFactor {
     let matrix: Matrix<i32> = Matrix::with_capacity(103, 101, 0);
 
@@ -53,7 +109,7 @@ fn safe_add(upos: &UPos, pos: &Pos, height: usize, width: usize) -> UPos {
     let x = (upos.0 as i32 + pos.0).rem_euclid(height as i32);
     let y = (upos.1 as i32 + pos.1).rem_euclid(width as i32);
 
-    UPos(x as usize,y as usize)
+    UPos::new(x as usize,y as usize)
 }
 
 fn get_quadrant(upos: &UPos, height: usize, width: usize) -> usize {
@@ -82,19 +138,13 @@ fn part2(input: &Input) -> i32 {
     // xxxx
     // xxxx
     // xxxx
-    let mask = Mask::new(vec![
-        Pos(0,0),Pos(0,1),Pos(0,2),Pos(0,3),
-        Pos(1,0),Pos(1,1),Pos(1,2),Pos(1,3),
-        Pos(2,0),Pos(2,1),Pos(2,2),Pos(2,3),
-        Pos(3,0),Pos(3,1),Pos(3,2),Pos(3,3),
-    ]);
+    let mask = Mask::square(4);
 
     let mut matrix: Matrix<i32> = Matrix::with_capacity(103, 101, 0);
     for robot in &robots {
         matrix[robot.p] += 1;
     }
 
-    let mut best_times = Vec::new();
     for sec in 1..10403 {
         for robot in &mut robots {
             matrix[robot.p] -= 1;
@@ -115,34 +165,12 @@ fn part2(input: &Input) -> i32 {
                 .sum();
 
             if check == 16 {
-                best_times.push(sec);
-                break;
+                return sec;
             }
         }
-        if best_times.len() >= 5 {
-            break;
-        }
-    }
-
-    for i in &best_times {
-        let mut matrix = Matrix::with_capacity(103,101,0);
-        for robot in input {
-            let end_pos = simulate_robot(&matrix, robot, *i);
-            matrix[end_pos] += 1;
-        }
-        println!("after {}s", i);
-        let mut debug_matrix = Matrix::with_capacity(103,101,' ');
-        for (val, upos) in matrix.give_upos() {
-            match *val {
-                1 => debug_matrix[upos] = '*',
-                2.. => debug_matrix[upos] = '#',
-                _ => (),
-            }
-        }
-        println!("{debug_matrix}");
     }
 
-    *best_times.first().unwrap()
+    panic!("no tree within a full cycle")
 }
 
 /*
@@ -152,58 +180,12 @@ fn standard_deviation(num: &[i64]) -> i64 {
 }
 */
 
-fn parse(contents: &str) -> Input {
-    let mut robots = Vec::new();
-    for line in contents.lines() {
-        let mut scanner = Scanner::new(line);
-
-        if !scanner.try_pattern("p=") {
-            continue;
-        }
-
-        let py = scanner.try_u32();
-        let py = match py {
-            Some(num) => num as usize,
-            None => continue,
-        };
-
-        if !scanner.take(&',') {
-            continue;
-        }
-
-        let px = scanner.try_u32();
-        let px = match px {
-            Some(num) => num as usize,
-            None => continue,
-        };
-
-        
-        if !scanner.try_pattern(" v=") {
-            continue;
-        }
-
-        let vy = scanner.try_i32();
-        let vy = match vy {
-            Some(num) => num,
-            None => continue,
-        };
-
-        if !scanner.take(&',') {
-            continue;
-        }
-
-        let vx = scanner.try_i32();
-        let vx = match vx {
-            Some(num) => num,
-            None => continue,
-        };
-
-        let robot = Robot { p: UPos(px,py), v: Pos(vx,vy) };
-
-        robots.push(robot);
-    }
+fn parse(contents: &str) -> Result<Input, Error> {
+    let robots = Scanner::new(contents).extract_all::<(usize, usize, i32, i32)>("p={},{} v={},{}")?;
 
-    robots
+    Ok(robots.into_iter()
+        .map(|(py, px, vy, vx)| Robot { p: UPos::new(px,py), v: Pos::new(vx,vy) })
+        .collect())
 }
 
 
@@ -215,10 +197,10 @@ mod tests {
     fn test_simulation() {
         let matrix = Matrix::with_capacity(7, 11, 0);
 
-        let robot = Robot { p: UPos(4,2), v: Pos(-3,2) };
+        let robot = Robot { p: UPos::new(4,2), v: Pos::new(-3,2) };
 
         let end_pos = simulate_robot(&matrix, &robot, 5);
 
-        assert_eq!(end_pos, UPos(3,1));
+        assert_eq!(end_pos, UPos::new(3,1));
     }
 }
@@ -1,26 +1,6 @@
-use core::time;
-use std::{fs::read_to_string, io, thread::sleep};
+use std::{fs::read_to_string, io};
 
-use crate::{matrix::Matrix, position::{Pos, UPos}};
-
-#[derive(Debug)]
-enum Dir {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Dir {
-    fn get_offset(&self) -> Pos {
-        match self {
-            Dir::Up => Pos(-1,0),
-            Dir::Down => Pos(1,0),
-            Dir::Left => Pos(0,-1),
-            Dir::Right => Pos(0,1),
-        }
-    }
-}
+use crate::{matrix::Matrix, position::{Dir, UPos}, render::{Color, Glyph, Renderer}, viz::{self, Player}};
 
 type Input = (UPos, Matrix<char>, Vec<Dir>);
 type Cost = i64;
@@ -41,14 +21,7 @@ fn part1(contents: &Input) -> Cost {
 }
 
 fn try_move(pos: &UPos, map: &mut Matrix<char>, dir: &Dir) -> Option<UPos> {
-    let offset = dir.get_offset();
-
-    let next_pos: Pos = Pos::from(*pos) + offset;
-    let next_pos: Result<UPos, _> = next_pos.try_into();
-    let next_pos = match next_pos {
-        Ok(pos) => pos,
-        Err(_e) => return None,
-    };
+    let next_pos = pos.checked_add_dir(*dir)?;
 
     match map.get(&next_pos) {
         Some(char) => {
@@ -91,20 +64,21 @@ fn calculate_costs(map: &Matrix<char>) -> Cost {
         .sum()
 }
 
-#[cfg(debug_assertions)]
-const WAIT_TIME: u64 = 32;
+/// Robot in red and boxes in yellow
+fn render_warehouse(map: &Matrix<char>) -> Renderer<'_, char> {
+    Renderer::new(map)
+        .with_glyphs(|c| match c {
+            '@' => Glyph::new(c).colored(Color::Red),
+            'O' | '[' | ']' => Glyph::new(c).colored(Color::Yellow),
+            _ => Glyph::new(c),
+        })
+}
 
 fn part2(contents: &Input) -> Cost {
     let mut robot_pos = contents.0;
     let mut map = contents.1.clone();
     let moves = &contents.2;
 
-    #[cfg(debug_assertions)]
-    {
-        println!("{map}");
-        sleep(time::Duration::from_millis(WAIT_TIME));
-        print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
-    }
     for dir in moves {
         let next_pos = look_ahead(&robot_pos, dir);
         if let Some(pos) = next_pos {
@@ -113,22 +87,36 @@ fn part2(contents: &Input) -> Cost {
                 robot_pos = pos;
             }
         }
-        #[cfg(debug_assertions)]
-        {
-            println!("{map}");
-            sleep(time::Duration::from_millis(WAIT_TIME));
-            print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
-        }
     }
 
     calculate_costs_wide(&map)
 }
 
-fn look_ahead(pos: &UPos, dir: &Dir) -> Option<UPos> {
-    let offset = dir.get_offset();
+/// Same as part2, one move per frame.
+fn visualize(contents: &Input) -> io::Result<()> {
+    let mut robot_pos = contents.0;
+    let mut moves = contents.2.iter();
+
+    let mut player = Player::from_fn(contents.1.clone(), |map| {
+        let dir = moves.next()?;
 
-    let next_pos: Pos = Pos::from(*pos) + offset;
-    next_pos.try_into().ok()
+        let mut map = map.clone();
+        if let Some(pos) = look_ahead(&robot_pos, dir) {
+            if able_move_wide(&pos, &map, dir) {
+                move_wide(&robot_pos, &mut map, dir);
+                robot_pos = pos;
+            }
+        }
+
+        Some(map)
+    })
+    .with_renderer(|map| render_warehouse(map).to_string());
+
+    player.run()
+}
+
+fn look_ahead(pos: &UPos, dir: &Dir) -> Option<UPos> {
+    pos.checked_add_dir(*dir)
 }
 
 fn able_move_wide(pos: &UPos, map: &Matrix<char>, dir: &Dir) -> bool {
@@ -360,6 +348,10 @@ pub fn answer() -> Result<(), io::Error>{
     let part2_res = part2(&p2_input);
     println!("result = {part2_res}");
 
+    if viz::enabled() {
+        visualize(&p2_input)?;
+    }
+
     Ok(())
 }
 
@@ -374,7 +366,7 @@ fn parse(contents: &str) -> Input {
     let x = robot_pos / width;
     let y = robot_pos % width;
 
-    let robot_pos = UPos(x,y);
+    let robot_pos = UPos::new(x,y);
 
     let rows:Vec<char> = map.chars().collect();
 
@@ -383,15 +375,7 @@ fn parse(contents: &str) -> Input {
 
     let rest = rest
         .chars()
-        .map(|c| {
-            match c {
-                '^' => Dir::Up,
-                'v' => Dir::Down,
-                '<' => Dir::Left,
-                '>' => Dir::Right,
-                _ => unreachable!("input is clean"),
-            }
-        })
+        .map(|c| Dir::from_char(c).expect("input is clean"))
         .collect();
 
     (robot_pos, map, rest)
@@ -408,7 +392,7 @@ fn parse_p2(contents: &str) -> Input {
     let x = robot_pos / width;
     let y = robot_pos % width;
 
-    let robot_pos = UPos(x,y);
+    let robot_pos = UPos::new(x,y);
 
     let rows:Vec<char> = map.chars().flat_map(|c| {
         match c {
@@ -423,15 +407,7 @@ fn parse_p2(contents: &str) -> Input {
 
     let rest = rest
         .chars()
-        .map(|c| {
-            match c {
-                '^' => Dir::Up,
-                'v' => Dir::Down,
-                '<' => Dir::Left,
-                '>' => Dir::Right,
-                _ => unreachable!("input is clean"),
-            }
-        })
+        .map(|c| Dir::from_char(c).expect("input is clean"))
         .collect();
 
     (robot_pos, map, rest)
@@ -1,6 +1,6 @@
-use std::{cmp::Ordering, collections::{BinaryHeap, HashSet}, fs::read_to_string, io};
+use std::{cmp::Ordering, collections::BinaryHeap, fs::read_to_string, io};
 
-use crate::{matrix::Matrix, position::{Dir, Pos, UPos}};
+use crate::{bit_matrix::BitMatrix, matrix::Matrix, position::{Dir, Pos, UPos, Vec2}};
 
 type Input = (Matrix<char>, UPos, UPos);
 type Cost = u64;
@@ -53,8 +53,8 @@ fn part1(input: &Input) -> Cost {
     map_costs[end].cost
 }
 
-fn homemade_dijkstra(input: Input) -> (Matrix<CellInfo>, HashSet<UPos>) {
-    let (mut map, start, end) = input;
+fn homemade_dijkstra(input: Input) -> (Matrix<CellInfo>, BitMatrix) {
+    let (map, start, end) = input;
 
     let mut map_costs: Matrix<CellInfo> = Matrix::with_capacity(map.row_count(), map.width(), CellInfo::default());
     map_costs[start] = CellInfo::new(0);
@@ -66,7 +66,7 @@ fn homemade_dijkstra(input: Input) -> (Matrix<CellInfo>, HashSet<UPos>) {
     let curr_cell = Status { cost:0, pos:start, dir:Dir::Right, path: vec![start] };
     heap.push(curr_cell);
 
-    let mut tiles: HashSet<UPos> = HashSet::new();
+    let mut tiles = BitMatrix::new(map.row_count(), map.width());
     while !heap.is_empty() {
         let curr_status = heap.pop().unwrap();
 
@@ -75,7 +75,9 @@ fn homemade_dijkstra(input: Input) -> (Matrix<CellInfo>, HashSet<UPos>) {
                 Ordering::Less | Ordering::Equal => {
                     map_costs[end].cost = curr_status.cost;
 
-                    tiles.extend(curr_status.path.into_iter());
+                    for tile in &curr_status.path {
+                        tiles.insert(tile);
+                    }
                     continue;
                 },
                 Ordering::Greater => continue,
@@ -84,8 +86,7 @@ fn homemade_dijkstra(input: Input) -> (Matrix<CellInfo>, HashSet<UPos>) {
 
         for dir in &all_dirs {
             let res = map_costs.look_ahead(&curr_status.pos, dir);
-            if res.is_some() {
-                let (next_cell, next_pos) = res.unwrap();
+            if let Some((next_cell, next_pos)) = res {
                 if map[next_pos] == '#' {
                     // Cant go through walls :c
                     continue;
@@ -135,12 +136,6 @@ fn homemade_dijkstra(input: Input) -> (Matrix<CellInfo>, HashSet<UPos>) {
         }
     }
 
-    map[end] = 'O';
-
-    for tile in &tiles {
-        map[*tile] = 'O';
-    }
-    
     (map_costs, tiles)
 }
 
@@ -148,8 +143,8 @@ fn rotation_cost(initial: Dir, next: Dir) -> Cost {
     let initial_pos: Pos = initial.into();
     let next_pos: Pos = next.into();
     match initial_pos + next_pos {
-        Pos(0,0) => 2000,
-        Pos(0,_) | Pos(_,0) => 0,
+        Vec2(0,0) => 2000,
+        Vec2(0,_) | Vec2(_,0) => 0,
         _ => 1000,
     }
 }
@@ -168,7 +163,7 @@ fn part2(input: &Input) -> Count {
 
     // Now that we found all the best paths, lets count the tiles
 
-    tiles.len()
+    tiles.count_ones()
 }
 
 pub fn answer() -> Result<(), io::Error>{
@@ -195,13 +190,13 @@ fn parse(input: String) -> Input {
 
     let x = start / width;
     let y = start % width;
-    let spos = UPos(x,y);
+    let spos = UPos::new(x,y);
 
     let end = rows.find("E").unwrap();
 
     let x = end / width;
     let y = end % width;
-    let epos = UPos(x,y);
+    let epos = UPos::new(x,y);
 
     let rows = rows.chars().collect();
     let map = Matrix::new(rows, width);
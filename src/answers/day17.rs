@@ -1,6 +1,6 @@
 use std::{cmp::Reverse, collections::BinaryHeap, fs::read_to_string, io};
 
-use crate::scanner::Scanner;
+use crate::scanner::{Error, Scanner};
 
 type Register = u64;
 type Op = u8;
@@ -15,6 +15,7 @@ struct Program {
 }
 
 impl Program {
+    #[allow(dead_code)]
     fn new(a: Register, b: Register, c: Register, ops: Vec<Op>) -> Self {
         Self { a, b, c, ops, ip: 0 }
     }
@@ -109,7 +110,7 @@ type Input = Program;
 pub fn answer() -> Result<(), io::Error>{
     let contents = read_to_string("input/day17.txt")?; // input/dayxx.txt
 
-    let input = parse(contents);
+    let input = parse(contents)?;
 
     // println!("{input:?}");
     
@@ -134,38 +135,24 @@ pub fn answer() -> Result<(), io::Error>{
     Ok(())
 }
 
-fn parse(contents: String) -> Input {
+fn parse(contents: String) -> Result<Input, Error> {
     let mut scanner = Scanner::new(&contents);
 
-    while *scanner.pop().unwrap() != ':' {}
-    scanner.take(&' ');
-    let a = scanner.try_u32().unwrap() as u64;
-
-    while *scanner.pop().unwrap() != ':' {}
-    scanner.take(&' ');
-    let b = scanner.try_u32().unwrap() as u64;
-
-    while *scanner.pop().unwrap() != ':' {}
-    scanner.take(&' ');
-    let c = scanner.try_u32().unwrap() as u64;
-
-    while *scanner.pop().unwrap() != ':' {}
-    scanner.take(&' ');
-
-    let mut ops = Vec::new();
-    while !scanner.is_done() {
-        let num = scanner.try_u32();
-        if let Some(val) = num {
-            ops.push(val as u8);
-            // pop the ,
-            scanner.pop();
-        } else {
-            // pop the last '\n'
-            scanner.pop();
-        }
-    }
+    let mut register = |name: &str| -> Result<Register, Error> {
+        scanner.expect(&format!("Register {name}: "))?;
+        let val = scanner.try_int()?;
+        scanner.skip_ws();
+        Ok(val)
+    };
+
+    let a = register("A")?;
+    let b = register("B")?;
+    let c = register("C")?;
+
+    scanner.expect("Program: ")?;
+    let ops = scanner.sep_by(|s| s.try_int::<u8>(), ",")?;
 
-    Program { a, b, c, ops, ip:0 }
+    Ok(Program { a, b, c, ops, ip:0 })
 }
 
 fn part1(input: &Input) -> Vec<Op> {
@@ -4,14 +4,14 @@ use std::{fs::read_to_string, io};
 
 use crate::matrix::Matrix;
 use crate::position::{Dir, UPos};
-use crate::scanner::Scanner;
+use crate::scanner::{Error, Scanner};
 
 type Input = Vec<UPos>;
 
 pub fn answer() -> Result<(), io::Error>{
     let contents = read_to_string("input/day18.txt")?; // input/dayxx.txt
     
-    let input = parse(contents);
+    let input = parse(contents)?;
     
     println!("Part1:");
     let now = Instant::now();
@@ -30,32 +30,27 @@ pub fn answer() -> Result<(), io::Error>{
     Ok(())
 }
 
-fn parse(contents: String) -> Input {
+fn parse(contents: String) -> Result<Input, Error> {
     let mut scanner = Scanner::new(&contents);
 
     let mut list = Vec::new();
     while !scanner.is_done() {
-        let y = scanner.try_u32().unwrap() as usize;
+        let y = scanner.try_int::<usize>()?;
+        scanner.expect(",")?;
+        let x = scanner.try_int::<usize>()?;
 
-        // pop the ','
-        scanner.pop();
-        
-        let x = scanner.try_u32().unwrap() as usize;
-
-        list.push(UPos(x,y));
-
-        // pop the '\n'
-        scanner.pop();
+        list.push(UPos::new(x,y));
+        scanner.skip_ws();
     }
 
-    list
+    Ok(list)
 }
 
 type Steps = u32;
 
 fn part1(input: &Input) -> Steps {
-    let start = UPos(0,0);
-    let end = UPos(70,70);
+    let start = UPos::new(0,0);
+    let end = UPos::new(70,70);
 
     let mut map = Matrix::with_capacity(71, 71, '.');
 
@@ -138,8 +133,7 @@ fn homemade_dijkstra(map: &mut Matrix<char>, start: UPos, end: UPos) -> (Matrix<
             }
 
             let res = map_costs.look_ahead(&curr_status.pos, dir);
-            if res.is_some() {
-                let (next_cell, next_pos) = res.unwrap();
+            if let Some((next_cell, next_pos)) = res {
                 if map[next_pos] == '#' {
                     // Cant go through walls :c
                     continue;
@@ -167,8 +161,8 @@ fn homemade_dijkstra(map: &mut Matrix<char>, start: UPos, end: UPos) -> (Matrix<
 }
 
 fn part2(input: &Input) -> (usize, usize) {
-    let start = UPos(0,0);
-    let end = UPos(70,70);
+    let start = UPos::new(0,0);
+    let end = UPos::new(70,70);
 
     let map = Matrix::with_capacity(71, 71, '.');
 
@@ -1,10 +1,12 @@
 use std::fmt::Display;
 use std::{fs::read_to_string, io};
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::BinaryHeap;
 use std::time::Instant;
 
 use crate::matrix::{self, Matrix};
 use crate::position::{Dir, UPos};
+#[cfg(feature = "serde")]
+use crate::persist;
 
 type Input = (Matrix<char>, UPos, UPos);
 
@@ -38,13 +40,13 @@ fn parse(contents: String) -> Input {
 
     let x = start / width;
     let y = start % width;
-    let spos = UPos(x,y);
+    let spos = UPos::new(x,y);
 
     let end = rows.find("E").unwrap();
 
     let x = end / width;
     let y = end % width;
-    let epos = UPos(x,y);
+    let epos = UPos::new(x,y);
 
     let rows = rows.chars().collect();
     let map = Matrix::new(rows, width);
@@ -55,10 +57,10 @@ fn parse(contents: String) -> Input {
 type Count = usize;
 
 fn part1(input: &Input) -> Count {
-    let (mut map, start, end) = input.clone();
+    let (map, start, end) = input.clone();
 
     // get the time from start to end with no cheats
-    let map_costs = homemade_dijkstra(&mut map, start, end);
+    let map_costs = cost_map(&map, start, end);
 
     find_cheats(&map, &map_costs, start, end, 2, 100)
 }
@@ -66,6 +68,7 @@ fn part1(input: &Input) -> Count {
 type Cost = u32;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct CellInfo {
     cost: Cost,
     dir: Dir,
@@ -108,7 +111,25 @@ impl Ord for Status {
     }
 }
 
-fn homemade_dijkstra(map: &mut Matrix<char>, start: UPos, end: UPos) -> Matrix<CellInfo> {
+/// With the `serde` feature the costs of the real input are kept on disk,
+/// recomputed whenever the map changes.
+#[cfg(feature = "serde")]
+fn cost_map(map: &Matrix<char>, start: UPos, end: UPos) -> Matrix<CellInfo> {
+    let key = (map.as_slice(), map.row_count(), map.width(), start, end);
+
+    persist::cached("target/cache/day20_costs.bin", &key, || homemade_dijkstra(map, start, end))
+        .unwrap_or_else(|e| {
+            eprintln!("Couldn't use the cost cache: {e}");
+            homemade_dijkstra(map, start, end)
+        })
+}
+
+#[cfg(not(feature = "serde"))]
+fn cost_map(map: &Matrix<char>, start: UPos, end: UPos) -> Matrix<CellInfo> {
+    homemade_dijkstra(map, start, end)
+}
+
+fn homemade_dijkstra(map: &Matrix<char>, start: UPos, end: UPos) -> Matrix<CellInfo> {
     let mut map_costs: Matrix<CellInfo> = Matrix::with_capacity(map.row_count(), map.width(), CellInfo::default());
     map_costs[start] = CellInfo::new(0, Dir::Right);
 
@@ -134,8 +155,7 @@ fn homemade_dijkstra(map: &mut Matrix<char>, start: UPos, end: UPos) -> Matrix<C
 
             let res = map_costs.look_ahead_mut(&curr_status.pos, dir);
             let mut next_dir = Dir::Right;
-            if res.is_some() {
-                let (next_cell, next_pos) = res.unwrap();
+            if let Some((next_cell, next_pos)) = res {
                 if map[next_pos] == '#' {
                     // Cant go through walls :c
                     continue;
@@ -170,74 +190,25 @@ fn find_cheats(
     max_cheat_distance: usize,
     savings: Cost,
 ) -> Count {
-    let mut good_cheats = 0;
     // Move through the map_costs following the dir
-    let mut status = Status { cost:0, pos:start, dir:Dir::Right };
-    while status.pos != end {
-        let cell = &map_costs[status.pos];
-
-        status.cost = cell.cost;
-        status.dir = cell.dir;
-
-        let mut endings = HashSet::new();
-
-        // Move to wherever we can reach
-        let valid_ends = map.give_upos()
-            .filter_map(|(val, upos)| {
-                if *val == '#' || status.pos == upos {
-                    return None;
-                }
-                let distance = matrix::manhattan_distance(&status.pos, &upos);
-                if distance <= max_cheat_distance {
-                    Some(upos)
-                } else {
-                    None
-                }
-            });
-        
-        endings.extend(valid_ends);
-
-        //let mut dedup_endings = Vec::new();
-        //let find_upos = |list: &Vec<(UPos,u32)>, upos: &UPos| {
-        //    for (i, (v,_d)) in list.iter().enumerate() {
-        //        if *v == *upos {
-        //            return Some(i);
-        //        }
-        //    }
-        //
-        //    None
-        //};
-        //for (upos, cheat_cost) in endings {
-        //    match find_upos(&dedup_endings, &upos) {
-        //        Some(i) => {
-        //            let (_old_upos, old_cost) = dedup_endings[i];
-        //            if cheat_cost < old_cost {
-        //                dedup_endings[i].1 = cheat_cost;
-        //            }
-        //        },
-        //        None => dedup_endings.push((upos, cheat_cost)),
-        //    }
-        //}
-        for upos in endings {
-            let cheat_cost = matrix::manhattan_distance(&status.pos, &upos) as Cost;
-            if map_costs[upos].cost > status.cost
-            && map_costs[upos].cost - status.cost > savings + (cheat_cost - 1) {
-                good_cheats += 1;
-            }
-        }
+    let mut path = vec![start];
+    let mut upos = start;
+    while upos != end {
+        let dir = map_costs[upos].dir;
 
-        let (_val, upos) = map.look_ahead(&status.pos, &status.dir).unwrap();
-        status.pos = upos;
+        (_, upos) = map.look_ahead(&upos, &dir).unwrap();
+        path.push(upos);
     }
 
-    good_cheats
+    // There's only one track, so the cost of a cell is its index on the path
+    matrix::count_shortcuts(&path, max_cheat_distance, savings as usize)
 }
 
 fn part2(input: &Input) -> Count {
-    let (mut map, start, end) = input.clone();
+    let (map, start, end) = input.clone();
 
     // get the time from start to end with no cheats
-    let map_costs = homemade_dijkstra(&mut map, start, end);
+    let map_costs = cost_map(&map, start, end);
 
     find_cheats(&map, &map_costs, start, end, 20, 100)
 }
@@ -267,10 +238,10 @@ mod tests {
 
         let input = parse(contents.to_string());
 
-        let (mut map, start, end) = input.clone();
+        let (map, start, end) = input.clone();
 
         // get the time from start to end with no cheats
-        let map_costs = homemade_dijkstra(&mut map, start, end);
+        let map_costs = homemade_dijkstra(&map, start, end);
 
         let result = find_cheats(&map, &map_costs, start, end, 2, 20);
 
@@ -298,10 +269,10 @@ mod tests {
 
         let input = parse(contents.to_string());
 
-        let (mut map, start, end) = input.clone();
+        let (map, start, end) = input.clone();
 
         // get the time from start to end with no cheats
-        let map_costs = homemade_dijkstra(&mut map, start, end);
+        let map_costs = homemade_dijkstra(&map, start, end);
 
         let result = find_cheats(&map, &map_costs, start, end, 20, 50);
 
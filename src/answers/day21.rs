@@ -4,7 +4,7 @@ use std::{fs::read_to_string, io};
 use std::time::Instant;
 
 use crate::matrix::Matrix;
-use crate::position::{Pos, UPos};
+use crate::position::{Pos, UPos, Vec2};
 
 type Input = Vec<String>;
 
@@ -133,8 +133,8 @@ impl Numpad {
             for j in 0..pad.width() {
                 for i2 in 0..pad.row_count() {
                     for j2 in 0..pad.width() {
-                        let from_val = pad[UPos(i,j)];
-                        let to_val = pad[UPos(i2,j2)];
+                        let from_val = pad[UPos::new(i,j)];
+                        let to_val = pad[UPos::new(i2,j2)];
                         let from = Self::val2pos(&from_val);
                         let to = Self::val2pos(&to_val);
                         if from.is_none() || to.is_none() {
@@ -153,15 +153,15 @@ impl Numpad {
     }
 
     fn offset2transition(start: &UPos, end: &UPos) -> String {
-        let from_pos: Pos = (*start).into();
-        let to_pos: Pos = (*end).into();
+        let from_pos = Pos::try_from(*start).unwrap();
+        let to_pos = Pos::try_from(*end).unwrap();
         let offset = to_pos-from_pos;
-        let Pos(x,y) = offset;
+        let Vec2(x,y) = offset;
 
         // protect from edge cases:
-        if let UPos(3,_h) = *start {
+        if let Vec2(3,_h) = *start {
             // we're going to pass over the gap
-            if let UPos(_v,0) = *end {
+            if let Vec2(_v,0) = *end {
                 let mut ret = String::new();
                 // go up for safety
                 let vert = "^".repeat(x.unsigned_abs() as usize);
@@ -173,9 +173,9 @@ impl Numpad {
                 return ret;
             }
         }
-        if let UPos(_v,0) = *start {
+        if let Vec2(_v,0) = *start {
             // we're going to pass over the gap
-            if let UPos(3,_h) = *end {
+            if let Vec2(3,_h) = *end {
                 let mut ret = String::new();
                 // go right for safety
                 let hori = ">".repeat(y.unsigned_abs() as usize);
@@ -231,11 +231,11 @@ impl Numpad {
 
     fn val2pos(val: &char) -> Option<UPos> {
         match val {
-            '7'..='9' => Some(UPos(0, (val.to_digit(10).unwrap()-7) as usize)),
-            '4'..='6' => Some(UPos(1, (val.to_digit(10).unwrap()-4) as usize)),
-            '1'..='3' => Some(UPos(2, (val.to_digit(10).unwrap()-1) as usize)),
-            '0' => Some(UPos(3,1)),
-            'A' => Some(UPos(3,2)),
+            '7'..='9' => Some(UPos::new(0, (val.to_digit(10).unwrap()-7) as usize)),
+            '4'..='6' => Some(UPos::new(1, (val.to_digit(10).unwrap()-4) as usize)),
+            '1'..='3' => Some(UPos::new(2, (val.to_digit(10).unwrap()-1) as usize)),
+            '0' => Some(UPos::new(3,1)),
+            'A' => Some(UPos::new(3,2)),
             _ => None,
         }
     }
@@ -270,6 +270,7 @@ impl Numpad {
         translation
     } 
 
+    #[allow(dead_code)]
     fn print(&self, curr_val: &char) {
         for (i, val)  in self.pad.iter().enumerate() {
             if i % self.pad.width() == 0 {
@@ -312,8 +313,8 @@ impl Keypad {
             for j in 0..pad.width() {
                 for i2 in 0..pad.row_count() {
                     for j2 in 0..pad.width() {
-                        let from_val = pad[UPos(i,j)];
-                        let to_val = pad[UPos(i2,j2)];
+                        let from_val = pad[UPos::new(i,j)];
+                        let to_val = pad[UPos::new(i2,j2)];
                         let from = Self::val2pos(&from_val);
                         let to = Self::val2pos(&to_val);
                         if from.is_none() || to.is_none() {
@@ -332,16 +333,16 @@ impl Keypad {
     }
 
     fn offset2transition(start: &UPos, end: &UPos) -> String {
-        let from_pos: Pos = (*start).into();
-        let to_pos: Pos = (*end).into();
+        let from_pos = Pos::try_from(*start).unwrap();
+        let to_pos = Pos::try_from(*end).unwrap();
         let offset = to_pos-from_pos;
-        let Pos(x,y) = offset;
+        let Vec2(x,y) = offset;
 
         // protect from edge cases:
-        if let UPos(0,_h) = *start {
+        if let Vec2(0,_h) = *start {
             // we're going to pass over the gap
             // and end in '<'
-            if let UPos(1,0) = *end {
+            if let Vec2(1,0) = *end {
                 let mut ret = String::new();
                 // go down for safety
                 let vert = "v".repeat(x.unsigned_abs() as usize);
@@ -353,10 +354,10 @@ impl Keypad {
                 return ret;
             }
         }
-        if let UPos(1,0) = *start {
+        if let Vec2(1,0) = *start {
             // we're going to pass over the gap
             // starting from '<'
-            if let UPos(0,_h) = *end {
+            if let Vec2(0,_h) = *end {
                 let mut ret = String::new();
                 // go right for safety
                 let hori = ">".repeat(y.unsigned_abs() as usize);
@@ -409,11 +410,11 @@ impl Keypad {
 
     fn val2pos(val: &char) -> Option<UPos> {
         match val {
-            '^' => Some(UPos(0,1)),
-            'A' => Some(UPos(0,2)),
-            '<' => Some(UPos(1,0)),
-            'v' => Some(UPos(1,1)),
-            '>' => Some(UPos(1,2)),
+            '^' => Some(UPos::new(0,1)),
+            'A' => Some(UPos::new(0,2)),
+            '<' => Some(UPos::new(1,0)),
+            'v' => Some(UPos::new(1,1)),
+            '>' => Some(UPos::new(1,2)),
             _ => None,
         }
     }
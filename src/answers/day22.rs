@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::{fs::read_to_string, io};
 use std::time::Instant;
+use crate::thread_pool::ThreadPool;
 
 type Input = String;
 
@@ -32,18 +33,31 @@ fn parse(contents: String) -> Input {
 
 type Secret = u64;
 fn part1(input: &Input) -> Secret {
-    let mut sum = 0;
-    let mut cache: HashMap<Secret, Secret> = HashMap::new();
-    
-    for val in input.lines().map(|line| line.parse::<Secret>().expect("should be number")) {
-        let mut next = val;
-        for _i in 0..2000 {
-            next = evolve_number(&mut cache, next);
+    let buyers: Vec<Secret> = input.lines()
+        .map(|line| line.parse::<Secret>().expect("should be number"))
+        .collect();
+
+    let pool: ThreadPool = ThreadPool::default();
+
+    // One cache per chunk, the buyers are only borrowed.
+    let chunk_size = buyers.len().div_ceil(pool.size()).max(1);
+    pool.par_chunks(&buyers, chunk_size, |_, chunk| {
+        let mut cache: HashMap<Secret, Secret> = HashMap::new();
+        let mut sum = 0;
+
+        for val in chunk {
+            let mut next = *val;
+            for _i in 0..2000 {
+                next = evolve_number(&mut cache, next);
+            }
+            sum += next;
         }
-        sum += next;
-    }
 
-    sum
+        sum
+    })
+    .expect("evolve_number shouldn't panic")
+    .into_iter()
+    .sum()
 }
 
 fn evolve_number(cache: &mut HashMap<Secret,Secret>, secret: Secret) -> Secret {
@@ -5,7 +5,7 @@ use std::str::FromStr;
 use std::{fs::read_to_string, io};
 use std::time::Instant;
 
-use crate::scanner::Scanner;
+use crate::scanner::{Error, Scanner};
 
 type Wire = String;
 type Wires = HashMap<Wire, bool>;
@@ -99,7 +99,7 @@ type Input = (Wires, Gates);
 pub fn answer() -> Result<(), io::Error>{
     let contents = read_to_string("input/day24.txt")?; // input/dayxx.txt
     
-    let input = parse(contents);
+    let input = parse(contents)?;
     
     println!("Part1:");
     let now = Instant::now();
@@ -118,46 +118,46 @@ pub fn answer() -> Result<(), io::Error>{
     Ok(())
 }
 
-fn parse(contents: String) -> Input {
-    let (wires_str, gates_str) = contents.split_once("\n\n").unwrap();
+fn parse_gate(scanner: &mut Scanner) -> Result<Gate, Error> {
+    let a = scanner.ident()?;
+    scanner.skip_ws();
+    let op = scanner.ident()?;
+    let op = Ops::from_str(op).expect("should be valid Ops in input");
+    scanner.skip_ws();
+    let b = scanner.ident()?;
+
+    scanner.skip_ws();
+    scanner.expect("->")?;
+    scanner.skip_ws();
+    let target = scanner.ident()?;
+    scanner.skip_ws();
+
+    Ok(Gate::new(a.to_string(),op,b.to_string(),target.to_string()))
+}
+
+fn parse(contents: String) -> Result<Input, Error> {
+    let mut scanner = Scanner::new(&contents);
 
     let mut wires = Wires::new();
-    for line in wires_str.lines() {
-        let name = &line[0..3];
-        let val = &line[5..6] == "1";
+    // The wires end at the blank line before the gates.
+    while !scanner.is_done() && !scanner.take(&'\n') {
+        let name = scanner.ident()?;
+        scanner.expect(": ")?;
+        let val = scanner.try_int::<u8>()? == 1;
+        if !scanner.is_done() {
+            scanner.expect("\n")?;
+        }
 
         *wires.entry(name.to_string()).or_default() = val;
     }
 
     let mut gates = Gates::new();
-    let mut scanner = Scanner::new(gates_str);
     while !scanner.is_done() {
-        let a = scanner.popn(3).unwrap();
-        scanner.pop();
-        let op = match scanner.popn(3) {
-            Some(str) => {
-                if &str == "OR " {
-                    Ops::Or
-                } else {
-                    scanner.pop();
-                    Ops::from_str(&str).expect("should be valid Ops in input")
-                }
-            },
-            None => unreachable!("Input is well constructed"),
-        };
-        let b = scanner.popn(3).unwrap();
-        scanner.pop();
-        scanner.pop();
-        scanner.pop();
-        scanner.pop();
-        let target = scanner.popn(3).unwrap();
-        scanner.pop();
-
-        let gate = Gate::new(a,op,b,target);
+        let gate = parse_gate(&mut scanner)?;
         gates.push(gate);
     }
 
-    (wires, gates)
+    Ok((wires, gates))
 }
 
 type Decimal = u64;
@@ -441,6 +441,7 @@ fn part2(input: &Input) -> Vec<String> {
     swaps
 }
 
+#[allow(dead_code)]
 fn test_adder(num: &str, input: &Input) {
     println!("testing {num}");
 
@@ -497,6 +498,7 @@ fn test_adder(num: &str, input: &Input) {
     println!("z = {}", wires[&format!("z{num}")]);
 }
 
+#[allow(dead_code)]
 fn print_dependencies(node: &String, gate_lut: &HashMap<String, Gate>) {
     let mut q = VecDeque::new();
     q.push_back((0, node));
@@ -580,10 +582,19 @@ hwm AND bqk -> z03
 tgd XOR rvg -> z12
 tnw OR pbm -> gnj".to_string();
 
-        let input = parse(contents);
+        let input = parse(contents).unwrap();
 
         let result = part1(&input);
 
         assert_eq!(result, 2024);
     }
+
+    #[test]
+    fn test_parse_error_line() {
+        let contents = "x00: 1\nx01: 0\ny00: x\n\nx00 AND y00 -> z00".to_string();
+
+        let error = parse(contents).unwrap_err();
+
+        assert_eq!((error.line(), error.column()), (3, 6));
+    }
 }
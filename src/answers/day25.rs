@@ -83,6 +83,6 @@ fn key_fits(key: &Key, lock: &Lock) -> bool {
     true
 }
 
-fn part2(input: &Input) -> String {
+fn part2(_input: &Input) -> String {
     "FINISHED AOC 2024! :D".to_string()
 }
@@ -0,0 +1,334 @@
+use std::ops::Index;
+use crate::position::{Pos, UPos, Vec2};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Bits packed in u64 words.
+///
+/// Every word carries the generation it was written in, and words from
+/// an older generation read as zero. That's what makes `clear_all` O(1).
+#[derive(Debug, Clone)]
+struct Bits {
+    words: Vec<u64>,
+    stamps: Vec<u32>,
+    generation: u32,
+    ones: usize,
+}
+
+impl Bits {
+    fn new(len: usize) -> Self {
+        let word_count = len.div_ceil(WORD_BITS);
+        Self {
+            words: vec![0; word_count],
+            stamps: vec![0; word_count],
+            generation: 0,
+            ones: 0,
+        }
+    }
+
+    fn word(&self, i: usize) -> u64 {
+        if self.stamps[i] == self.generation {
+            self.words[i]
+        } else {
+            0
+        }
+    }
+
+    fn get(&self, bit: usize) -> bool {
+        self.word(bit / WORD_BITS) & (1 << (bit % WORD_BITS)) != 0
+    }
+
+    /// Returns the previous value
+    fn set(&mut self, bit: usize, value: bool) -> bool {
+        let i = bit / WORD_BITS;
+        let mask = 1 << (bit % WORD_BITS);
+
+        let word = self.word(i);
+        let previous = word & mask != 0;
+
+        self.words[i] = if value { word | mask } else { word & !mask };
+        self.stamps[i] = self.generation;
+
+        match (previous, value) {
+            (false, true) => self.ones += 1,
+            (true, false) => self.ones -= 1,
+            _ => (),
+        }
+
+        previous
+    }
+
+    fn clear_all(&mut self) {
+        self.ones = 0;
+
+        match self.generation.checked_add(1) {
+            Some(generation) => self.generation = generation,
+            None => {
+                // Stamps are about to repeat, do an actual clear.
+                self.generation = 0;
+                self.words.fill(0);
+                self.stamps.fill(0);
+            }
+        }
+    }
+}
+
+/// A `Matrix<bool>` that takes one bit per cell.
+#[derive(Debug, Clone)]
+pub struct BitMatrix {
+    bits: Bits,
+    row_count: usize,
+    width: usize,
+}
+
+impl BitMatrix {
+    /// All cells start unset.
+    pub fn new(row_count: usize, width: usize) -> Self {
+        Self {
+            bits: Bits::new(row_count * width),
+            row_count,
+            width,
+        }
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    fn offset(&self, upos: &UPos) -> Option<usize> {
+        let Vec2(x,y) = *upos;
+
+        if (0..self.row_count).contains(&x) && (0..self.width).contains(&y) {
+            Some(x * self.width + y)
+        } else {
+            None
+        }
+    }
+
+    fn offset_pos(&self, pos: &Pos) -> Option<usize> {
+        let upos = UPos::try_from(*pos).ok()?;
+        self.offset(&upos)
+    }
+
+    pub fn get(&self, upos: &UPos) -> Option<bool> {
+        self.offset(upos).map(|bit| self.bits.get(bit))
+    }
+
+    pub fn get_pos(&self, pos: &Pos) -> Option<bool> {
+        self.offset_pos(pos).map(|bit| self.bits.get(bit))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `upos` is outside the matrix.
+    pub fn set(&mut self, upos: &UPos, value: bool) {
+        let bit = self.offset(upos).unwrap();
+        self.bits.set(bit, value);
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `pos` is outside the matrix.
+    pub fn set_pos(&mut self, pos: &Pos, value: bool) {
+        let bit = self.offset_pos(pos).unwrap();
+        self.bits.set(bit, value);
+    }
+
+    /// Set the cell, returning true if it wasn't set before. Like `HashSet::insert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `upos` is outside the matrix.
+    pub fn insert(&mut self, upos: &UPos) -> bool {
+        let bit = self.offset(upos).unwrap();
+        !self.bits.set(bit, true)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `pos` is outside the matrix.
+    pub fn insert_pos(&mut self, pos: &Pos) -> bool {
+        let bit = self.offset_pos(pos).unwrap();
+        !self.bits.set(bit, true)
+    }
+
+    /// Unset every cell in O(1)
+    pub fn clear_all(&mut self) {
+        self.bits.clear_all();
+    }
+
+    /// Amount of cells set, in O(1)
+    pub fn count_ones(&self) -> usize {
+        self.bits.ones
+    }
+}
+
+impl Index<UPos> for BitMatrix {
+    type Output = bool;
+
+    fn index(&self, index: UPos) -> &Self::Output {
+        if self.get(&index).unwrap() { &true } else { &false }
+    }
+}
+
+impl Index<Pos> for BitMatrix {
+    type Output = bool;
+
+    fn index(&self, index: Pos) -> &Self::Output {
+        if self.get_pos(&index).unwrap() { &true } else { &false }
+    }
+}
+
+/// A BitMatrix with `depth` bits per cell, for states like (position, direction).
+#[derive(Debug, Clone)]
+pub struct BitMatrix3 {
+    bits: Bits,
+    row_count: usize,
+    width: usize,
+    depth: usize,
+}
+
+impl BitMatrix3 {
+    /// All cells start unset.
+    pub fn new(row_count: usize, width: usize, depth: usize) -> Self {
+        Self {
+            bits: Bits::new(row_count * width * depth),
+            row_count,
+            width,
+            depth,
+        }
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn offset(&self, upos: &UPos, layer: usize) -> Option<usize> {
+        let Vec2(x,y) = *upos;
+
+        if (0..self.row_count).contains(&x) && (0..self.width).contains(&y) && layer < self.depth {
+            Some((x * self.width + y) * self.depth + layer)
+        } else {
+            None
+        }
+    }
+
+    fn offset_pos(&self, pos: &Pos, layer: usize) -> Option<usize> {
+        let upos = UPos::try_from(*pos).ok()?;
+        self.offset(&upos, layer)
+    }
+
+    pub fn get(&self, upos: &UPos, layer: usize) -> Option<bool> {
+        self.offset(upos, layer).map(|bit| self.bits.get(bit))
+    }
+
+    pub fn get_pos(&self, pos: &Pos, layer: usize) -> Option<bool> {
+        self.offset_pos(pos, layer).map(|bit| self.bits.get(bit))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `upos` or `layer` is outside the matrix.
+    pub fn set(&mut self, upos: &UPos, layer: usize, value: bool) {
+        let bit = self.offset(upos, layer).unwrap();
+        self.bits.set(bit, value);
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `pos` or `layer` is outside the matrix.
+    pub fn set_pos(&mut self, pos: &Pos, layer: usize, value: bool) {
+        let bit = self.offset_pos(pos, layer).unwrap();
+        self.bits.set(bit, value);
+    }
+
+    /// Set the cell, returning true if it wasn't set before. Like `HashSet::insert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `upos` or `layer` is outside the matrix.
+    pub fn insert(&mut self, upos: &UPos, layer: usize) -> bool {
+        let bit = self.offset(upos, layer).unwrap();
+        !self.bits.set(bit, true)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `pos` or `layer` is outside the matrix.
+    pub fn insert_pos(&mut self, pos: &Pos, layer: usize) -> bool {
+        let bit = self.offset_pos(pos, layer).unwrap();
+        !self.bits.set(bit, true)
+    }
+
+    /// Unset every cell in O(1)
+    pub fn clear_all(&mut self) {
+        self.bits.clear_all();
+    }
+
+    /// Amount of cells set, in O(1)
+    pub fn count_ones(&self) -> usize {
+        self.bits.ones
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_clear() {
+        let mut visited = BitMatrix::new(10, 13);
+
+        assert!(visited.insert(&UPos::new(9,12)));
+        assert!(!visited.insert(&UPos::new(9,12)));
+        assert!(visited.insert_pos(&Pos::new(0,0)));
+        assert_eq!(visited.count_ones(), 2);
+        assert!(visited[UPos::new(9,12)]);
+        assert_eq!(visited.get_pos(&Pos::new(-1,0)), None);
+
+        visited.clear_all();
+        assert_eq!(visited.count_ones(), 0);
+        assert!(!visited[Pos::new(0,0)]);
+
+        visited.set(&UPos::new(0,0), true);
+        visited.set(&UPos::new(0,0), false);
+        assert_eq!(visited.count_ones(), 0);
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut visited = BitMatrix3::new(3, 3, 4);
+
+        assert!(visited.insert(&UPos::new(1,1), 2));
+        assert_eq!(visited.get(&UPos::new(1,1), 1), Some(false));
+        assert_eq!(visited.get(&UPos::new(1,1), 2), Some(true));
+        assert_eq!(visited.get(&UPos::new(1,1), 4), None);
+
+        visited.clear_all();
+        assert!(visited.insert_pos(&Pos::new(1,1), 2));
+    }
+
+    #[test]
+    fn test_generation_wrap() {
+        let mut bits = Bits::new(100);
+        bits.generation = u32::MAX;
+        bits.set(70, true);
+
+        bits.clear_all();
+
+        assert_eq!(bits.generation, 0);
+        assert!(!bits.get(70));
+    }
+}
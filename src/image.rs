@@ -0,0 +1,388 @@
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}, sync::atomic::{AtomicBool, Ordering}};
+use crate::matrix::Matrix;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turned on by the `--export` flag. Days only write images under target/viz when it's set.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(value: bool) {
+    ENABLED.store(value, Ordering::Relaxed);
+}
+
+pub type Rgb = [u8; 3];
+
+/// A picture of a Matrix, where every cell is a `scale`x`scale` square.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn from_matrix<T>(matrix: &Matrix<T>, scale: usize, palette: impl Fn(&T) -> Rgb) -> Self {
+        assert!(scale > 0);
+
+        let width = matrix.width() * scale;
+        let height = matrix.row_count() * scale;
+        let cells: Vec<Rgb> = matrix.iter().map(palette).collect();
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in cells.chunks(matrix.width()) {
+            for _ in 0..scale {
+                for color in row {
+                    for _ in 0..scale {
+                        pixels.push(*color);
+                    }
+                }
+            }
+        }
+
+        Self { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Binary PPM (P6)
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.pixels.iter().flatten());
+
+        bytes
+    }
+
+    /// 8 bit RGB PNG. The image data goes uncompressed inside the zlib stream.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut bytes = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+        let mut header = Vec::new();
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // bit depth, color type (RGB), compression, filter, interlace
+        header.extend([8, 2, 0, 0, 0]);
+        png_chunk(&mut bytes, b"IHDR", &header);
+
+        // Every scanline starts with its filter type, 0 means none
+        let mut raw = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for row in self.pixels.chunks(self.width) {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+        png_chunk(&mut bytes, b"IDAT", &zlib_stored(&raw));
+
+        png_chunk(&mut bytes, b"IEND", &[]);
+
+        bytes
+    }
+
+    /// Save as PNG if the extension says so, PPM otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let bytes = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => self.to_png(),
+            _ => self.to_ppm(),
+        };
+
+        fs::write(path, bytes)
+    }
+}
+
+impl<T> Matrix<T> {
+    pub fn to_image(&self, scale: usize, palette: impl Fn(&T) -> Rgb) -> Image {
+        Image::from_matrix(self, scale, palette)
+    }
+}
+
+fn png_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend((data.len() as u32).to_be_bytes());
+
+    let start = bytes.len();
+    bytes.extend(kind);
+    bytes.extend(data);
+    let crc = crc32(&bytes[start..]);
+
+    bytes.extend(crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+/// zlib stream made of stored (not compressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x78, 0x01];
+
+    let blocks: Vec<&[u8]> = data.chunks(u16::MAX as usize).collect();
+    if blocks.is_empty() {
+        bytes.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    for (i, block) in blocks.iter().enumerate() {
+        let last = i + 1 == blocks.len();
+        let len = block.len() as u16;
+
+        bytes.push(last as u8);
+        bytes.extend(len.to_le_bytes());
+        bytes.extend((!len).to_le_bytes());
+        bytes.extend(*block);
+    }
+
+    bytes.extend(adler32(data).to_be_bytes());
+
+    bytes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ppm,
+    Png,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Ppm => "ppm",
+            Format::Png => "png",
+        }
+    }
+}
+
+/// Keeps every frame of a simulation so it can be looked at afterwards.
+#[derive(Debug, Default)]
+pub struct FrameRecorder {
+    frames: Vec<Image>,
+}
+
+impl FrameRecorder {
+    pub fn new() -> Self {
+        Self { frames: Vec::new() }
+    }
+
+    pub fn record<T>(&mut self, matrix: &Matrix<T>, scale: usize, palette: impl Fn(&T) -> Rgb) {
+        self.frames.push(Image::from_matrix(matrix, scale, palette));
+    }
+
+    pub fn frames(&self) -> &[Image] {
+        &self.frames
+    }
+
+    /// Write `frame_0000.ext`, `frame_0001.ext`, ... inside `dir`.
+    pub fn save_sequence(&self, dir: impl AsRef<Path>, format: Format) -> io::Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                let path = dir.join(format!("frame_{i:04}.{}", format.extension()));
+                frame.save(&path)?;
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Animated GIF looping forever, `delay` is in hundredths of a second.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there are no frames, the frames don't share a size,
+    /// they're bigger than 65535 pixels on a side
+    /// or they use more than 256 colors in total.
+    pub fn to_gif(&self, delay: u16) -> io::Result<Vec<u8>> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let first = self.frames.first().ok_or_else(|| invalid("No frames recorded"))?;
+        if self.frames.iter().any(|frame| frame.width != first.width || frame.height != first.height) {
+            return Err(invalid("Every frame should have the same size"));
+        }
+        let too_big = |_| invalid("GIF frames can't be wider or taller than 65535 pixels");
+        let width = u16::try_from(first.width).map_err(too_big)?;
+        let height = u16::try_from(first.height).map_err(too_big)?;
+
+        let mut palette: HashMap<Rgb, u8> = HashMap::new();
+        let mut colors: Vec<Rgb> = Vec::new();
+        for color in self.frames.iter().flat_map(|frame| &frame.pixels) {
+            if !palette.contains_key(color) {
+                if colors.len() == 256 {
+                    return Err(invalid("GIF frames can't use more than 256 colors"));
+                }
+                palette.insert(*color, colors.len() as u8);
+                colors.push(*color);
+            }
+        }
+        colors.resize(256, [0, 0, 0]);
+
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        // global color table of 256 colors, background 0, no aspect ratio
+        bytes.extend([0xf7, 0, 0]);
+        bytes.extend(colors.iter().flatten());
+
+        // loop forever
+        bytes.extend([0x21, 0xff, 0x0b]);
+        bytes.extend(b"NETSCAPE2.0");
+        bytes.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+
+        for frame in &self.frames {
+            bytes.extend([0x21, 0xf9, 0x04, 0x00]);
+            bytes.extend(delay.to_le_bytes());
+            bytes.extend([0x00, 0x00]);
+
+            bytes.push(0x2c);
+            bytes.extend([0, 0, 0, 0]);
+            bytes.extend(width.to_le_bytes());
+            bytes.extend(height.to_le_bytes());
+            bytes.push(0x00);
+
+            let indexes: Vec<u8> = frame.pixels.iter().map(|color| palette[color]).collect();
+            bytes.push(8);
+            for block in lzw_literals(&indexes).chunks(255) {
+                bytes.push(block.len() as u8);
+                bytes.extend(block);
+            }
+            bytes.push(0x00);
+        }
+
+        bytes.push(0x3b);
+
+        Ok(bytes)
+    }
+
+    pub fn save_gif(&self, path: impl AsRef<Path>, delay: u16) -> io::Result<()> {
+        fs::write(path, self.to_gif(delay)?)
+    }
+}
+
+/// GIF flavoured LZW that only ever emits literals.
+///
+/// Sending a clear code before the table grows past 9 bit codes
+/// means we never have to build the dictionary ourselves.
+fn lzw_literals(indexes: &[u8]) -> Vec<u8> {
+    const CLEAR: u32 = 256;
+    const END: u32 = 257;
+    const CODE_SIZE: u32 = 9;
+    const MAX_RUN: usize = 254;
+
+    let mut bytes = Vec::new();
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+
+    let mut emit = |code: u32| {
+        buffer |= code << bits;
+        bits += CODE_SIZE;
+        while bits >= 8 {
+            bytes.push(buffer as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+    };
+
+    for run in indexes.chunks(MAX_RUN) {
+        emit(CLEAR);
+        for index in run {
+            emit(*index as u32);
+        }
+    }
+    emit(END);
+
+    if bits > 0 {
+        bytes.push(buffer as u8);
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(val: &char) -> Rgb {
+        match val {
+            '#' => [0, 0, 0],
+            _ => [255, 255, 255],
+        }
+    }
+
+    #[test]
+    fn test_ppm() {
+        let matrix = Matrix::new(vec!['#', '.'], 2);
+
+        let ppm = matrix.to_image(1, palette).to_ppm();
+
+        assert_eq!(ppm, b"P6\n2 1\n255\n\x00\x00\x00\xff\xff\xff");
+    }
+
+    #[test]
+    fn test_scale() {
+        let matrix = Matrix::new(vec!['#', '.'], 2);
+
+        let image = matrix.to_image(3, palette);
+
+        assert_eq!((image.width(), image.height()), (6, 3));
+    }
+
+    #[test]
+    fn test_png_checksums() {
+        // Known values for "IEND" and "Wikipedia"
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let matrix = Matrix::new(vec!['#', '.', '.', '#'], 2);
+        let png = matrix.to_image(2, palette).to_png();
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[png.len() - 12..], b"\x00\x00\x00\x00IEND\xae\x42\x60\x82");
+    }
+
+    #[test]
+    fn test_gif() {
+        let mut recorder = FrameRecorder::new();
+        recorder.record(&Matrix::new(vec!['#', '.'], 2), 1, palette);
+        recorder.record(&Matrix::new(vec!['.', '#'], 2), 1, palette);
+
+        let gif = recorder.to_gif(10).unwrap();
+
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(gif.last(), Some(&0x3b));
+
+        recorder.record(&Matrix::new(vec!['.'], 1), 1, palette);
+        assert!(recorder.to_gif(10).is_err());
+    }
+
+    #[test]
+    fn test_gif_too_wide() {
+        let mut recorder = FrameRecorder::new();
+        recorder.record(&Matrix::new(vec!['#'; 65536], 65536), 1, palette);
+
+        assert!(recorder.to_gif(10).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::{cell::OnceCell, collections::HashMap};
+
+use crate::scanner::{Error, Patterns, Scanner};
+
+/// One instruction found in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    Enable,
+    Disable,
+    /// Something like "mul(2,4)"
+    Call { name: &'a str, args: Vec<i64> },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Keyword {
+    Enable,
+    Disable,
+    Call,
+}
+
+type Handler<S> = Box<dyn FnMut(&mut S, &[i64])>;
+type ArgParser = Box<dyn Fn(&mut Scanner) -> Result<i64, Error>>;
+
+/// Runs the instructions hidden in some text, skipping everything else.
+///
+/// The lexing is done by the `Scanner`, this only keeps track of whether
+/// instructions are enabled and hands calls to the registered handlers,
+/// which update the accumulator `S`.
+pub struct Interpreter<S> {
+    keywords: Vec<(String, Keyword)>,
+    /// Built from `keywords` the first time it's needed.
+    patterns: OnceCell<Patterns>,
+    handlers: HashMap<String, Handler<S>>,
+    arg: ArgParser,
+    enabled: bool,
+    state: S,
+}
+
+impl<S> Interpreter<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            keywords: Vec::new(),
+            patterns: OnceCell::new(),
+            handlers: HashMap::new(),
+            arg: Box::new(|s| s.try_int()),
+            enabled: true,
+            state,
+        }
+    }
+
+    /// Instructions that turn the calls on and off, like "do()" and "don't()".
+    pub fn toggles(&mut self, enable: &str, disable: &str) -> &mut Self {
+        self.add_keyword(enable, Keyword::Enable);
+        self.add_keyword(disable, Keyword::Disable)
+    }
+
+    /// Handle calls like "`name`(1,2,3)". Arguments are integers.
+    pub fn on(&mut self, name: &str, handler: impl FnMut(&mut S, &[i64]) + 'static) -> &mut Self {
+        self.handlers.insert(name.to_string(), Box::new(handler));
+        self.add_keyword(&format!("{name}("), Keyword::Call)
+    }
+
+    /// How call arguments are read, any integer by default.
+    /// A call with an argument it rejects isn't an instruction.
+    pub fn args(&mut self, parser: impl Fn(&mut Scanner) -> Result<i64, Error> + 'static) -> &mut Self {
+        self.arg = Box::new(parser);
+        self
+    }
+
+    fn add_keyword(&mut self, keyword: &str, kind: Keyword) -> &mut Self {
+        self.keywords.push((keyword.to_string(), kind));
+        self.patterns.take();
+
+        self
+    }
+
+    fn patterns(&self) -> &Patterns {
+        self.patterns.get_or_init(|| {
+            let keywords: Vec<&str> = self.keywords.iter().map(|(keyword, _)| keyword.as_str()).collect();
+            Patterns::new(&keywords)
+        })
+    }
+
+    /// Skip ahead to the next well formed instruction.
+    pub fn next_token<'a>(&self, scanner: &mut Scanner<'a>) -> Option<Token<'a>> {
+        loop {
+            let found = scanner.find_next(self.patterns())?;
+
+            let token = match self.keywords[found.pattern].1 {
+                Keyword::Enable => Some(Token::Enable),
+                Keyword::Disable => Some(Token::Disable),
+                Keyword::Call => scanner.attempt(|s| {
+                    let name = s.take_match(&found)?.strip_suffix('(')?;
+                    let args = s.sep_by(|s| (self.arg)(s), ",").ok()?;
+                    s.expect(")").ok()?;
+
+                    Some(Token::Call { name, args })
+                }),
+            };
+
+            match token {
+                Some(Token::Call { .. }) => return token,
+                Some(_) => {
+                    scanner.take_match(&found);
+                    return token;
+                },
+                // Nothing here, try again from the next char.
+                None => { scanner.pop(); },
+            }
+        }
+    }
+
+    pub fn step(&mut self, token: &Token) {
+        match token {
+            Token::Enable => self.enabled = true,
+            Token::Disable => self.enabled = false,
+            Token::Call { name, args } => {
+                if self.enabled {
+                    if let Some(handler) = self.handlers.get_mut(*name) {
+                        handler(&mut self.state, args);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Run every instruction in `text`.
+    pub fn run(&mut self, text: &str) -> &S {
+        let mut scanner = Scanner::new(text);
+
+        while let Some(token) = self.next_token(&mut scanner) {
+            self.step(&token);
+        }
+
+        &self.state
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn into_state(self) -> S {
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let mut interpreter = Interpreter::new(());
+        interpreter.toggles("on", "off").on("add", |_, _| {});
+
+        let mut scanner = Scanner::new("add(1)off?addd(2)add(3,-4)add(x)add()on");
+        let tokens: Vec<Token> = std::iter::from_fn(|| interpreter.next_token(&mut scanner)).collect();
+
+        assert_eq!(tokens, vec![
+            Token::Call { name: "add", args: vec![1] },
+            Token::Disable,
+            Token::Call { name: "add", args: vec![3, -4] },
+            Token::Call { name: "add", args: vec![] },
+            Token::Enable,
+        ]);
+    }
+
+    #[test]
+    fn test_run() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter
+            .toggles("on", "off")
+            .on("push", |stack: &mut Vec<i64>, args| stack.extend(args))
+            .on("sum", |stack, _| {
+                let sum = stack.drain(..).sum();
+                stack.push(sum);
+            });
+
+        interpreter.run("push(1,2)offpush(5)on push(3) sum() push(4)");
+
+        assert!(interpreter.enabled());
+        assert_eq!(interpreter.into_state(), vec![6, 4]);
+    }
+
+    #[test]
+    fn test_wide_keywords() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter
+            .toggles("sí", "no✋")
+            .on("añadir", |stack: &mut Vec<i64>, args| stack.extend(args));
+
+        interpreter.run("añadir(1)no✋añadir(2)síañadir(3)");
+
+        assert_eq!(interpreter.into_state(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_late_handler() {
+        let mut interpreter = Interpreter::new(0);
+        interpreter.on("add", |total: &mut i64, args| *total += args.iter().sum::<i64>());
+        interpreter.run("add(1)sub(2)");
+
+        interpreter.on("sub", |total, args| *total -= args.iter().sum::<i64>());
+        interpreter.run("add(1)sub(2)");
+
+        assert_eq!(*interpreter.state(), 0);
+    }
+}
@@ -1,8 +1,16 @@
 
 pub mod answers;
 pub mod matrix;
+pub mod bit_matrix;
 pub mod scanner;
+pub mod interpreter;
 pub mod thread_pool;
+pub mod point;
 pub mod position;
 pub mod debug_print;
+pub mod render;
+pub mod image;
+pub mod viz;
+#[cfg(feature = "serde")]
+pub mod persist;
 
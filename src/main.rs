@@ -1,7 +1,15 @@
-use std::{io, process::exit, time::Instant};
-use advent_of_code2024::answers;
+use std::{env, io, process::exit, time::Instant};
+use advent_of_code2024::{answers, image, viz};
 
 fn main() {
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--visualize" => viz::set_enabled(true),
+            "--export" => image::set_enabled(true),
+            _ => (),
+        }
+    }
+
     println!("Input the day to get the day's answer. (0 for all)");
 
     let stdin = io::stdin();
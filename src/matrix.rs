@@ -1,5 +1,5 @@
-use std::{collections::{HashSet, VecDeque}, fmt::{Debug, Display}, iter::Zip, ops::{Index, IndexMut, Mul}};
-use crate::position::{Pos, UPos, PosIter, UPosIter, Dir};
+use std::{collections::{HashSet, VecDeque}, fmt::{Debug, Display}, iter::Zip, ops::{Index, IndexMut, Mul, Range}};
+use crate::position::{Pos, UPos, Vec2, PosIter, UPosIter, Dir};
 
 #[derive(Debug, Clone, Copy)]
 struct AStarCell {
@@ -42,7 +42,7 @@ impl<T> Matrix<T> {
     }
 
     pub fn get(&self, upos: &UPos) -> Option<&T> {
-        let UPos(x,y) = *upos;
+        let Vec2(x,y) = *upos;
 
         if (0..self.width).contains(&y) {
             self.rows.get(x * self.width + y)
@@ -63,7 +63,7 @@ impl<T> Matrix<T> {
     }
 
     pub fn get_mut(&mut self, upos: &UPos) -> Option<&mut T> {
-        let UPos(x,y) = upos;
+        let Vec2(x,y) = upos;
 
         if (0..self.width).contains(y) {
             self.rows.get_mut(x * self.width + y)
@@ -84,23 +84,26 @@ impl<T> Matrix<T> {
     }
     
     pub fn look_ahead(&self, upos: &UPos, dir: &Dir) -> Option<(&T, UPos)> {
-        let offset = Pos::from(*upos) + Pos::from(*dir);
-        let val = self.get_pos(&offset)?;
+        let dims = UPos::new(self.row_count, self.width);
+        let offset = upos.offset_within(Pos::from(*dir), dims)?;
+        let val = self.get(&offset)?;
 
-        // We know it's a valid UPos because it's inside the matrix
-        let offset = offset.try_into().unwrap();
         Some((val, offset))
     }
 
     pub fn look_ahead_mut(&mut self, upos: &UPos, dir: &Dir) -> Option<(&mut T, UPos)> {
-        let offset = Pos::from(*upos) + Pos::from(*dir);
-        let val = self.get_mut_pos(&offset)?;
+        let dims = UPos::new(self.row_count, self.width);
+        let offset = upos.offset_within(Pos::from(*dir), dims)?;
+        let val = self.get_mut(&offset)?;
 
-        // We know it's a valid UPos because it's inside the matrix
-        let offset = offset.try_into().unwrap();
         Some((val, offset))
     }
 
+    /// All the cells, row after row.
+    pub fn as_slice(&self) -> &[T] {
+        &self.rows
+    }
+
     pub fn iter(&self) -> std::slice::Iter<'_, T> {
         self.rows.iter()
     }
@@ -110,21 +113,77 @@ impl<T> Matrix<T> {
     }
 
     pub fn give_upos(&self) -> Zip<std::slice::Iter<'_, T>, UPosIter> {
-        self.iter().zip(UPosIter::new(self.width))
+        self.iter().zip(self.positions())
     }
 
     pub fn give_upos_mut(&mut self) -> Zip<std::slice::IterMut<'_, T>, UPosIter> {
-        let width = self.width;
-        self.iter_mut().zip(UPosIter::new(width))
+        let positions = self.positions();
+        self.iter_mut().zip(positions)
     }
 
     pub fn give_pos(&self) -> Zip<std::slice::Iter<'_, T>, PosIter> {
-        self.iter().zip(PosIter::new(self.width))
+        self.iter().zip(PosIter::new(self.row_count, self.width))
     }
 
     pub fn give_pos_mut(&mut self) -> Zip<std::slice::IterMut<'_, T>, PosIter> {
-        let width = self.width;
-        self.iter_mut().zip(PosIter::new(width))
+        let positions = PosIter::new(self.row_count, self.width);
+        self.iter_mut().zip(positions)
+    }
+
+    /// Every position of the matrix, row by row, without looking at the cells.
+    pub fn positions(&self) -> UPosIter {
+        UPosIter::new(self.row_count, self.width)
+    }
+
+    /// Every position of the matrix, column by column.
+    pub fn positions_column_major(&self) -> UPosIter {
+        UPosIter::column_major(self.row_count, self.width)
+    }
+
+    /// Walk from `start` in steps of `step` until a cell matches `stop`
+    /// or we leave the matrix. `start` gets checked too.
+    ///
+    /// Gives back the cells walked over, and the one that stopped us
+    /// (None if we went out).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is zero, like `Pos::ray`.
+    pub fn cast(&self, start: Pos, step: impl Into<Pos>, stop: impl Fn(&T) -> bool) -> (Vec<Pos>, Option<Pos>) {
+        let mut visited = Vec::new();
+
+        for pos in start.ray(step) {
+            match self.get_pos(&pos) {
+                Some(val) if stop(val) => return (visited, Some(pos)),
+                Some(_) => visited.push(pos),
+                None => break,
+            }
+        }
+
+        (visited, None)
+    }
+
+    /// Every cell within manhattan distance `radius` of `center`, center included.
+    /// Only the diamond around `center` gets visited, row by row.
+    pub fn within_manhattan(&self, center: UPos, radius: usize) -> impl Iterator<Item = (&T, UPos)> + '_ {
+        let Vec2(cx, cy) = center;
+
+        span(cx, radius, self.row_count).flat_map(move |x| {
+            let remaining = radius - x.abs_diff(cx);
+
+            span(cy, remaining, self.width).map(move |y| (&self[UPos::new(x,y)], UPos::new(x,y)))
+        })
+    }
+
+    /// Every cell within chebyshev distance `radius` of `center`, center included.
+    /// Only the square around `center` gets visited, row by row.
+    pub fn within_chebyshev(&self, center: UPos, radius: usize) -> impl Iterator<Item = (&T, UPos)> + '_ {
+        let Vec2(cx, cy) = center;
+        let cols = span(cy, radius, self.width);
+
+        span(cx, radius, self.row_count).flat_map(move |x| {
+            cols.clone().map(move |y| (&self[UPos::new(x,y)], UPos::new(x,y)))
+        })
     }
 
     /// Incomplete right now
@@ -139,8 +198,8 @@ impl<T> Matrix<T> {
             return None;
         }
 
-        let mut cost = 0;
-        let mut path = Vec::new();
+        let cost = 0;
+        let path = Vec::new();
 
         if start == end {
             return Some((path, cost));
@@ -162,11 +221,8 @@ impl<T> Matrix<T> {
         open.push_back(first);
         open_set.insert(first);
 
-        let mut found_dest = false;
-
-        let card_dir = Mask::new(vec![Pos(-1,0),Pos(1,0),Pos(0,-1),Pos(0,1)]);
-        while !open.is_empty() {
-            let p@(_f,pos): AStarPair = open.pop_front()?;
+        let card_dir = Mask::cardinal();
+        while let Some(p@(_f,pos)) = open.pop_front() {
             open_set.remove(&p);
 
             closed[pos] = true;
@@ -175,16 +231,7 @@ impl<T> Matrix<T> {
             // .  N   .
             // W cell E
             // .  S   .
-
-            let (g_new, h_new, f_new): (usize, usize, usize);
-
-            let dirs = card_dir.apply_upos(pos, &cell_details);
-
-            for dir in dirs {
-                if let Some(cell) = dir {
-                    
-                }
-            }
+            let _neighbours = card_dir.apply_upos(pos, &cell_details);
         }
 
         Some((path, cost))
@@ -251,10 +298,10 @@ impl Matrix<f64> {
         let mut new_row = Vec::new();
         for row in 0..self.row_count {
             for col in 0..self.width {
-                new_row.push(self[UPos(row,col)]);
+                new_row.push(self[UPos::new(row,col)]);
             }
             for col in 0..rhs.width() {
-                new_row.push(rhs[UPos(row,col)]);
+                new_row.push(rhs[UPos::new(row,col)]);
             }
         }
 
@@ -267,7 +314,7 @@ impl Matrix<f64> {
         for row in 0..self.row_count {
             for og_col in 0..self.width {
                 if og_col >= col {
-                    new_row.push(self[UPos(row,og_col)]);
+                    new_row.push(self[UPos::new(row,og_col)]);
                 }
             }
         }
@@ -290,21 +337,21 @@ impl Matrix<f64> {
         while h < inverse.row_count && k < inverse.width {
             let row_max = argmax(&inverse, k).unwrap() ;
 
-            if is_near_zero(&inverse[UPos(row_max,k)]) {
+            if is_near_zero(&inverse[UPos::new(row_max,k)]) {
                 // no pivot, pass to next column
                 k += 1;
             } else {
                 inverse.swap_rows(h,row_max);
-                inverse.mul_row(h, 1./inverse[UPos(h,k)]);
+                inverse.mul_row(h, 1./inverse[UPos::new(h,k)]);
 
                 // do for all rows below pivot:
                 for i in h+1..inverse.row_count {
-                    let f = -(inverse[UPos(i,k)] / inverse[UPos(h,k)]);
+                    let f = -(inverse[UPos::new(i,k)] / inverse[UPos::new(h,k)]);
                     // fill with zeros the lower part of pivot column
-                    inverse[UPos(i,k)] = 0.0;
+                    inverse[UPos::new(i,k)] = 0.0;
                     // do for all remaining elements in current row
                     for j in k+1..inverse.width {
-                        inverse[UPos(i,j)] = inverse[UPos(i,j)] + inverse[UPos(h,j)] * f;
+                        inverse[UPos::new(i,j)] = inverse[UPos::new(i,j)] + inverse[UPos::new(h,j)] * f;
                     }
                 }
 
@@ -319,7 +366,7 @@ impl Matrix<f64> {
 
         while h > 0 && k > 0 {
             for row in 0..h {
-                let f = -(inverse[UPos(row,k)] / inverse[UPos(h,k)]);
+                let f = -(inverse[UPos::new(row,k)] / inverse[UPos::new(h,k)]);
                 inverse.add_rows(row, h, f);
             }
 
@@ -352,28 +399,59 @@ impl Matrix<f64> {
 
     pub fn swap_rows(&mut self, a: usize, b: usize) {
         for i in 0..self.width()  {
-            let aux = self[UPos(a,i)];
-            self[UPos(a,i)] = self[UPos(b,i)];
-            self[UPos(b,i)] = aux;
+            let aux = self[UPos::new(a,i)];
+            self[UPos::new(a,i)] = self[UPos::new(b,i)];
+            self[UPos::new(b,i)] = aux;
         }
     }
 
     pub fn add_rows(&mut self, a: usize, b: usize, k: f64) {
         for i in 0..self.width()  {
-            let val = self[UPos(b,i)] * k;
-            self[UPos(a,i)] += val;
+            let val = self[UPos::new(b,i)] * k;
+            self[UPos::new(a,i)] += val;
         }
     }
 
     pub fn mul_row(&mut self, a: usize, val: f64) {
         for i in 0..self.width()  {
-            self[UPos(a,i)] *= val;
+            self[UPos::new(a,i)] *= val;
         }
     }
 }
 
-pub fn manhattan_distance(start: &UPos, end: &UPos) -> usize {
-    start.0.abs_diff(end.0) + start.1.abs_diff(end.1)
+/// Count the pairs of cells in `path` that are at most `max_distance` apart
+/// and whose index gap exceeds that distance by at least `threshold`.
+///
+/// That is, the amount of shortcuts of length `max_distance` or less that
+/// save at least `threshold` steps along the path.
+pub fn count_shortcuts(path: &[UPos], max_distance: usize, threshold: usize) -> usize {
+    let Some(row_count) = path.iter().map(|upos| upos.0 + 1).max() else {
+        return 0;
+    };
+    let width = path.iter().map(|upos| upos.1 + 1).max().unwrap();
+
+    let mut indexes: Matrix<Option<usize>> = Matrix::with_capacity(row_count, width, None);
+    for (i, upos) in path.iter().enumerate() {
+        indexes[*upos] = Some(i);
+    }
+
+    path.iter()
+        .enumerate()
+        .map(|(i, upos)| {
+            indexes.within_manhattan(*upos, max_distance)
+                .filter(|(j, other)| match j {
+                    Some(j) => *j > i && (*j - i) as u128 >= threshold as u128 + upos.manhattan(other),
+                    None => false,
+                })
+                .count()
+        })
+        .sum()
+}
+
+/// Indices at most `reach` away from `center`, clipped to `0..len`.
+/// Empty when `len` is zero, so empty matrices have no neighbourhoods.
+fn span(center: usize, reach: usize, len: usize) -> Range<usize> {
+    center.saturating_sub(reach)..center.saturating_add(reach).saturating_add(1).min(len)
 }
 
 fn is_near_zero(val: &f64) -> bool {
@@ -384,10 +462,10 @@ fn argmax<T: std::cmp::PartialOrd + Clone>(matrix: &Matrix<T>, col: usize) -> Op
     if matrix.row_count() > 0 && col < matrix.width() {
         let column = col ;
         let mut max_row: usize = 0;
-        let mut max_val = matrix[UPos(max_row,column)].clone();
+        let mut max_val = matrix[UPos::new(max_row,column)].clone();
 
         for row in 1..matrix.row_count()  {
-            let val = matrix[UPos(row,column)].clone();
+            let val = matrix[UPos::new(row,column)].clone();
             if max_val.partial_cmp(&val).unwrap() == std::cmp::Ordering::Less {
                 max_row = row;
                 max_val = val;
@@ -413,7 +491,7 @@ impl Mul for Matrix<i64> {
                 for col in 0..rhs.width()  {
                     let mut value = 0;
                     for pos in 0..self.width  {
-                        value += self[UPos(row,pos)] * self[UPos(pos,col)];
+                        value += self[UPos::new(row,pos)] * self[UPos::new(pos,col)];
                     }
 
                     rows.push(value);
@@ -437,7 +515,7 @@ impl Mul for Matrix<f64> {
                 for col in 0..rhs.width()  {
                     let mut value = 0.0;
                     for pos in 0..self.width  {
-                        value += self[UPos(row,pos)] * self[UPos(pos,col)];
+                        value += self[UPos::new(row,pos)] * self[UPos::new(pos,col)];
                     }
 
                     rows.push(value);
@@ -549,6 +627,97 @@ impl Mask {
         }
     }
 
+    /// The 4 cardinal neighbours, in Up, Down, Left, Right order.
+    /// .X.
+    /// XPX
+    /// .X.
+    pub fn cardinal() -> Self {
+        Self::new(vec![Pos::new(-1,0),Pos::new(1,0),Pos::new(0,-1),Pos::new(0,1)])
+    }
+
+    /// The 4 diagonal neighbours, in UpLeft, UpRight, DownLeft, DownRight order.
+    /// X.X
+    /// .P.
+    /// X.X
+    pub fn diagonal() -> Self {
+        Self::new(vec![Pos::new(-1,-1),Pos::new(-1,1),Pos::new(1,-1),Pos::new(1,1)])
+    }
+
+    /// All 8 neighbours, the cardinal ones first and then the diagonals.
+    /// XXX
+    /// XPX
+    /// XXX
+    pub fn king() -> Self {
+        let mut relative_pos = Self::cardinal().relative_pos;
+        relative_pos.extend(Self::diagonal().relative_pos);
+
+        Self::new(relative_pos)
+    }
+
+    /// A `size`x`size` block with P in the top-left corner, row by row.
+    /// PXX
+    /// XXX
+    /// XXX
+    pub fn square(size: usize) -> Self {
+        let size = size as i32;
+        let relative_pos = (0..size)
+            .flat_map(|x| (0..size).map(move |y| Pos::new(x,y)))
+            .collect();
+
+        Self::new(relative_pos)
+    }
+
+    /// Every cell at exactly `radius` steps away, counting diagonal steps as one.
+    /// Never holds P, like `cardinal` and `king`, so `ring(0)` is empty.
+    /// XXXXX
+    /// X...X
+    /// X.P.X
+    /// X...X
+    /// XXXXX
+    pub fn ring(radius: usize) -> Self {
+        let r = radius as i32;
+        let relative_pos = (-r..=r)
+            .flat_map(|x| (-r..=r).map(move |y| Pos::new(x,y)))
+            .filter(|&Vec2(x,y)| x.abs().max(y.abs()) == r && (x,y) != (0,0))
+            .collect();
+
+        Self::new(relative_pos)
+    }
+
+    /// `len` cells going in the direction of `dir`, starting at P.
+    /// PXXX
+    pub fn ray(dir: Pos, len: usize) -> Self {
+        let relative_pos = (0..len as i32)
+            .map(|i| dir * i)
+            .collect();
+
+        Self::new(relative_pos)
+    }
+
+    /// Every cell within manhattan distance `radius`.
+    /// Never holds P, like `cardinal` and `king`, so `manhattan_ball(0)` is empty.
+    /// ..X..
+    /// .XXX.
+    /// XXPXX
+    /// .XXX.
+    /// ..X..
+    pub fn manhattan_ball(radius: usize) -> Self {
+        let r = radius as i32;
+        let relative_pos = (-r..=r)
+            .flat_map(|x| {
+                let remaining = r - x.abs();
+                (-remaining..=remaining).map(move |y| Pos::new(x,y))
+            })
+            .filter(|&pos| pos != Pos::new(0,0))
+            .collect();
+
+        Self::new(relative_pos)
+    }
+
+    pub fn relative_pos(&self) -> &[Pos] {
+        &self.relative_pos
+    }
+
     pub fn apply<'a, T>(&self, pos: Pos, matrix: &'a Matrix<T>) -> Vec<Option<&'a T>> {
         self.relative_pos
             .iter()
@@ -560,11 +729,20 @@ impl Mask {
     }
 
     pub fn apply_upos<'a, T>(&self, upos: UPos, matrix: &'a Matrix<T>) -> Vec<Option<&'a T>> {
+        match Pos::try_from(upos) {
+            Ok(pos) => self.apply(pos, matrix),
+            Err(_) => vec![None; self.relative_pos.len()],
+        }
+    }
+
+    /// Like `apply`, but skips the cells outside the matrix and
+    /// gives back the absolute position of the ones inside.
+    pub fn apply_with_pos<'a, T>(&self, pos: Pos, matrix: &'a Matrix<T>) -> Vec<(Pos, &'a T)> {
         self.relative_pos
             .iter()
-            .map(|&i| {
-                let rel_pos = i+Pos::from(upos);
-                matrix.get_pos(&rel_pos)
+            .filter_map(|&i| {
+                let rel_pos = i+pos;
+                matrix.get_pos(&rel_pos).map(|val| (rel_pos, val))
             })
             .collect()
     }
@@ -585,20 +763,20 @@ ARA";
 
         let matrix = Matrix::new(rows, width);
 
-        let mask = Mask::new(vec![Pos(-1,0),Pos(1,0),Pos(0,-1),Pos(0,1),]);
+        let mask = Mask::new(vec![Pos::new(-1,0),Pos::new(1,0),Pos::new(0,-1),Pos::new(0,1),]);
         for (val, pos) in matrix.give_pos() {
             let neighbours:Vec<Option<char>> = mask.apply(pos, &matrix)
                 .iter()
                 .map(|v| v.as_deref().copied())
                 .collect();
-            if pos == Pos(1,1) {
+            if pos == Pos::new(1,1) {
                 assert_eq!(
                     neighbours, 
                     vec![Some('R'),Some('R'),Some('R'),Some('R')]
                 );
             }
 
-            if pos == Pos(1,0) {
+            if pos == Pos::new(1,0) {
                 assert_eq!(
                     neighbours, 
                     vec![Some('A'),Some('A'),None,Some('R')]
@@ -609,6 +787,115 @@ ARA";
         }
     }
 
+    #[test]
+    fn test_masks_skip_the_centre() {
+        let centre = Pos::new(0,0);
+
+        assert!(Mask::ring(0).relative_pos().is_empty());
+        assert!(Mask::manhattan_ball(0).relative_pos().is_empty());
+        for radius in 1..4 {
+            assert!(!Mask::ring(radius).relative_pos().contains(&centre));
+            assert!(!Mask::manhattan_ball(radius).relative_pos().contains(&centre));
+        }
+    }
+
+    #[test]
+    fn test_mask_constructors() {
+        assert_eq!(Mask::king().relative_pos().len(), 8);
+        assert_eq!(Mask::square(4).relative_pos().len(), 16);
+        assert_eq!(Mask::ring(2).relative_pos().len(), 16);
+        assert_eq!(Mask::manhattan_ball(2).relative_pos().len(), 12);
+        assert_eq!(Mask::ring(1).relative_pos().len(), Mask::king().relative_pos().len());
+        assert_eq!(Mask::manhattan_ball(1).relative_pos().len(), Mask::cardinal().relative_pos().len());
+        assert_eq!(
+            Mask::ray(Pos::new(1,-1), 3).relative_pos(),
+            &[Pos::new(0,0),Pos::new(1,-1),Pos::new(2,-2)]
+        );
+    }
+
+    #[test]
+    fn test_mask_apply_with_pos() {
+        let input = "\
+ABC
+DEF
+GHI";
+        let width = 3;
+        let rows = input.replace("\n", "").chars().collect();
+
+        let matrix = Matrix::new(rows, width);
+
+        let neighbours: Vec<(Pos, char)> = Mask::king()
+            .apply_with_pos(Pos::new(0,0), &matrix)
+            .into_iter()
+            .map(|(pos, val)| (pos, *val))
+            .collect();
+
+        assert_eq!(neighbours, vec![(Pos::new(1,0),'D'),(Pos::new(0,1),'B'),(Pos::new(1,1),'E')]);
+    }
+
+    #[test]
+    fn test_within_manhattan() {
+        let matrix = Matrix::with_capacity(5, 5, 0);
+
+        let cells: Vec<UPos> = matrix.within_manhattan(UPos::new(2,2), 2)
+            .map(|(_val, upos)| upos)
+            .collect();
+        assert_eq!(cells.len(), 13);
+        assert!(cells.iter().all(|upos| upos.manhattan(&UPos::new(2,2)) <= 2));
+
+        let corner = matrix.within_manhattan(UPos::new(0,0), 1).count();
+        assert_eq!(corner, 3);
+    }
+
+    #[test]
+    fn test_within_chebyshev() {
+        let matrix = Matrix::with_capacity(5, 5, 0);
+
+        assert_eq!(matrix.within_chebyshev(UPos::new(2,2), 1).count(), 9);
+        assert_eq!(matrix.within_chebyshev(UPos::new(0,4), 2).count(), 9);
+    }
+
+    #[test]
+    fn test_within_empty() {
+        let matrix = Matrix::with_capacity(0, 0, 0);
+
+        assert_eq!(matrix.within_manhattan(UPos::new(0,0), 1).count(), 0);
+        assert_eq!(matrix.within_chebyshev(UPos::new(0,0), 1).count(), 0);
+    }
+
+    #[test]
+    fn test_cast() {
+        let matrix = Matrix::new("..#....".chars().collect(), 7);
+
+        let (visited, stop) = matrix.cast(Pos::new(0,0), Dir::Right, |c| *c == '#');
+        assert_eq!(visited, vec![Pos::new(0,0), Pos::new(0,1)]);
+        assert_eq!(stop, Some(Pos::new(0,2)));
+
+        let (visited, stop) = matrix.cast(Pos::new(0,3), Pos::new(0,2), |c| *c == '#');
+        assert_eq!(visited, vec![Pos::new(0,3), Pos::new(0,5)]);
+        assert_eq!(stop, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "non zero step")]
+    fn test_cast_standing_still() {
+        let matrix = Matrix::new("...".chars().collect(), 3);
+
+        let _ = matrix.cast(Pos::new(0,0), Pos::new(0,0), |c| *c == '#');
+    }
+
+    #[test]
+    fn test_count_shortcuts() {
+        // A U-turn:
+        // 0 1
+        // 3 2
+        let path = [UPos::new(0,0),UPos::new(0,1),UPos::new(1,1),UPos::new(1,0)];
+
+        // only 0 -> 3 saves 2 steps by being 1 apart
+        assert_eq!(count_shortcuts(&path, 1, 2), 1);
+        assert_eq!(count_shortcuts(&path, 1, 3), 0);
+    }
+
     #[test]
     fn test_gauss_jordan_inverse() {
         let rows = vec![2.,-1.,0.,-1.,2.,-1.,0.,-1.,2.];
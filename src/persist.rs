@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    path::Path,
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use crate::matrix::Matrix;
+
+/// On disk a Matrix is just its dimensions and the flat rows.
+#[derive(Serialize)]
+struct MatrixRef<'a, T> {
+    row_count: usize,
+    width: usize,
+    rows: &'a [T],
+}
+
+#[derive(Deserialize)]
+struct MatrixOwned<T> {
+    row_count: usize,
+    width: usize,
+    rows: Vec<T>,
+}
+
+impl<T: Serialize> Serialize for Matrix<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MatrixRef {
+            row_count: self.row_count(),
+            width: self.width(),
+            rows: self.as_slice(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Matrix<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let MatrixOwned { row_count, width, rows } = MatrixOwned::deserialize(deserializer)?;
+
+        if rows.is_empty() || width == 0 || rows.len() != row_count * width {
+            return Err(serde::de::Error::custom(format!(
+                "{} cells don't make a {row_count}x{width} matrix", rows.len()
+            )));
+        }
+
+        Ok(Matrix::new(rows, width))
+    }
+}
+
+fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+pub fn to_bytes<T: Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .map_err(invalid_data)
+}
+
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> io::Result<T> {
+    let (value, _read) = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+        .map_err(invalid_data)?;
+
+    Ok(value)
+}
+
+pub fn save<T: Serialize>(path: impl AsRef<Path>, value: &T) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, to_bytes(value)?)
+}
+
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> io::Result<T> {
+    from_bytes(&fs::read(path)?)
+}
+
+fn fingerprint(key: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Load the value saved at `path`, or compute it and save it there
+/// so the next run can skip the work.
+///
+/// `key` is whatever the value was computed from. Its hash is saved
+/// with the value, and a different key (or a file that doesn't decode)
+/// means computing it again.
+pub fn cached<T: Serialize + DeserializeOwned>(
+    path: impl AsRef<Path>,
+    key: &impl Hash,
+    compute: impl FnOnce() -> T,
+) -> io::Result<T> {
+    let path = path.as_ref();
+    let fingerprint = fingerprint(key);
+
+    if let Ok((saved, value)) = load::<(u64, T)>(path) {
+        if saved == fingerprint {
+            return Ok(value);
+        }
+    }
+
+    let value = compute();
+    save(path, &(fingerprint, &value))?;
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{Dir, Pos, UPos};
+
+    #[test]
+    fn test_matrix_round_trip() {
+        let matrix = Matrix::new(vec![1_u32, 2, 3, 4, 5, 6], 3);
+
+        let bytes = to_bytes(&matrix).unwrap();
+        let back: Matrix<u32> = from_bytes(&bytes).unwrap();
+
+        assert_eq!(back.width(), 3);
+        assert_eq!(back.row_count(), 2);
+        assert_eq!(back.as_slice(), matrix.as_slice());
+    }
+
+    #[test]
+    fn test_positions_round_trip() {
+        let value = (Pos::new(-3, 4), UPos::new(1, 2), Dir::Left);
+
+        let bytes = to_bytes(&value).unwrap();
+
+        assert_eq!(from_bytes::<(Pos, UPos, Dir)>(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_bad_dimensions() {
+        let bytes = to_bytes(&MatrixRef { row_count: 2, width: 2, rows: &[1_u8, 2, 3] }).unwrap();
+
+        assert!(from_bytes::<Matrix<u8>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_cached() {
+        let path = std::env::temp_dir().join(format!("aoc_cached_{}.bin", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let first: Vec<u32> = cached(&path, &"input", || vec![1, 2, 3]).unwrap();
+        let second: Vec<u32> = cached(&path, &"input", || unreachable!("should come from disk")).unwrap();
+        let changed: Vec<u32> = cached(&path, &"new input", || vec![4]).unwrap();
+        let third: Vec<u32> = cached(&path, &"new input", || unreachable!("should come from disk")).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(changed, vec![4]);
+        assert_eq!(third, changed);
+    }
+}
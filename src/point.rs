@@ -0,0 +1,211 @@
+use std::{num::TryFromIntError, ops::{Add, Mul, Neg, Sub}};
+
+/// A 2D point (or offset) over any number type.
+///
+/// `position::Pos` and `position::UPos` are the i32 and usize flavours.
+/// Ordering is lexicographic, first coordinate first.
+#[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec2<T>(pub T, pub T);
+
+/// Same as Vec2 with one more axis.
+#[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec3<T>(pub T, pub T, pub T);
+
+impl<T> Vec2<T> {
+    pub const fn new(x: T, y: T) -> Self {
+        Self (x,y)
+    }
+
+    /// Lossless conversion to another number type, like i32 to i64.
+    pub fn cast<U: From<T>>(self) -> Vec2<U> {
+        Vec2(self.0.into(), self.1.into())
+    }
+
+    /// Conversion that fails instead of truncating.
+    pub fn try_cast<U: TryFrom<T, Error = TryFromIntError>>(self) -> Result<Vec2<U>, TryFromIntError> {
+        Ok(Vec2(self.0.try_into()?, self.1.try_into()?))
+    }
+}
+
+impl<T> Vec3<T> {
+    pub const fn new(x: T, y: T, z: T) -> Self {
+        Self (x,y,z)
+    }
+
+    pub fn cast<U: From<T>>(self) -> Vec3<U> {
+        Vec3(self.0.into(), self.1.into(), self.2.into())
+    }
+
+    pub fn try_cast<U: TryFrom<T, Error = TryFromIntError>>(self) -> Result<Vec3<U>, TryFromIntError> {
+        Ok(Vec3(self.0.try_into()?, self.1.try_into()?, self.2.try_into()?))
+    }
+}
+
+impl<T: Add<Output = T>> Add for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self (self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self (self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self (self.0 * rhs, self.1 * rhs)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn neg(self) -> Self::Output {
+        Self (-self.0, -self.1)
+    }
+}
+
+impl<T: Add<Output = T>> Add for Vec3<T> {
+    type Output = Vec3<T>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self (self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Vec3<T> {
+    type Output = Vec3<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self (self.0 - rhs.0, self.1 - rhs.1, self.2 - rhs.2)
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Vec3<T> {
+    type Output = Vec3<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self (self.0 * rhs, self.1 * rhs, self.2 * rhs)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Vec3<T> {
+    type Output = Vec3<T>;
+
+    fn neg(self) -> Self::Output {
+        Self (-self.0, -self.1, -self.2)
+    }
+}
+
+/// Distances for every integer type.
+/// `$dist` is what `abs_diff` gives back, `$wide` has room for sums and squares of it,
+/// so no metric can overflow.
+macro_rules! impl_metrics {
+    ($($int:ty => $dist:ty, $wide:ty);* $(;)?) => {
+        $(
+            impl Vec2<$int> {
+                pub fn manhattan(&self, other: &Self) -> $wide {
+                    self.0.abs_diff(other.0) as $wide + self.1.abs_diff(other.1) as $wide
+                }
+
+                /// Steps a king would need
+                pub fn chebyshev(&self, other: &Self) -> $dist {
+                    self.0.abs_diff(other.0).max(self.1.abs_diff(other.1))
+                }
+
+                /// Squared euclidean distance, so it stays an integer
+                pub fn euclid_sq(&self, other: &Self) -> $wide {
+                    let dx = self.0.abs_diff(other.0) as $wide;
+                    let dy = self.1.abs_diff(other.1) as $wide;
+                    dx * dx + dy * dy
+                }
+            }
+
+            impl Vec3<$int> {
+                pub fn manhattan(&self, other: &Self) -> $wide {
+                    self.0.abs_diff(other.0) as $wide
+                        + self.1.abs_diff(other.1) as $wide
+                        + self.2.abs_diff(other.2) as $wide
+                }
+
+                pub fn chebyshev(&self, other: &Self) -> $dist {
+                    self.0.abs_diff(other.0)
+                        .max(self.1.abs_diff(other.1))
+                        .max(self.2.abs_diff(other.2))
+                }
+
+                pub fn euclid_sq(&self, other: &Self) -> $wide {
+                    let dx = self.0.abs_diff(other.0) as $wide;
+                    let dy = self.1.abs_diff(other.1) as $wide;
+                    let dz = self.2.abs_diff(other.2) as $wide;
+                    dx * dx + dy * dy + dz * dz
+                }
+            }
+        )*
+    };
+}
+
+impl_metrics! {
+    i32 => u32, u64;
+    i64 => u64, u128;
+    isize => usize, u128;
+    u32 => u32, u64;
+    u64 => u64, u128;
+    usize => usize, u128;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wide_coordinates() {
+        // day 13 part 2 sized offsets
+        let prize = Vec2(8400_i64, 5400) + Vec2(10_000_000_000_000, 10_000_000_000_000);
+
+        assert_eq!(prize - Vec2(8400, 5400), Vec2(10_000_000_000_000, 10_000_000_000_000));
+        assert_eq!(Vec2(1_i32, -2).cast::<i64>() * 3, Vec2(3, -6));
+        assert!(Vec2(-1_i32, 0).try_cast::<usize>().is_err());
+        assert_eq!(Vec2(3_u64, 4).try_cast::<usize>(), Ok(Vec2(3, 4)));
+    }
+
+    #[test]
+    fn test_vec3() {
+        let a = Vec3(1_i32, 2, 3);
+        let b = Vec3(-1, 0, 5);
+
+        assert_eq!(a + b, Vec3(0, 2, 8));
+        assert_eq!(-(a - b), Vec3(-2, -2, 2));
+        assert_eq!(a.manhattan(&b), 6);
+        assert_eq!(a.chebyshev(&b), 2);
+        assert_eq!(a.euclid_sq(&b), 12);
+    }
+
+    #[test]
+    fn test_metrics_dont_overflow() {
+        let far = Vec2(u32::MAX, u32::MAX);
+        assert_eq!(Vec2(0_u32, 0).manhattan(&far), 2 * u32::MAX as u64);
+
+        let far = Vec3(usize::MAX, usize::MAX, usize::MAX);
+        assert_eq!(Vec3(0_usize, 0, 0).manhattan(&far), 3 * usize::MAX as u128);
+        assert_eq!(Vec2(isize::MIN, 0).euclid_sq(&Vec2(isize::MAX, 0)), usize::MAX as u128 * usize::MAX as u128);
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut points = vec![Vec2(1, 0), Vec2(0, 5), Vec2(0, 1)];
+        points.sort();
+
+        assert_eq!(points, vec![Vec2(0, 1), Vec2(0, 5), Vec2(1, 0)]);
+    }
+}
@@ -1,163 +1,276 @@
-use std::{fmt::Display, ops::{Add, Mul, Sub}};
+use std::{fmt::Display, ops::Add};
 
-#[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq)]
-pub struct Pos (pub i32, pub i32);
+pub use crate::point::{Vec2, Vec3};
 
-impl Pos {
-    pub fn new(x:i32, y:i32) -> Self {
-        Self (x,y)
-    }
-}
+/// A position in a grid, or an offset between two of them.
+pub type Pos = Vec2<i32>;
 
-impl Sub for Pos {
-    type Output = Pos;
+/// A position that's known to be inside (or at least not before) a grid.
+pub type UPos = Vec2<usize>;
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self (self.0 - rhs.0, self.1 - rhs.1)
+impl TryFrom<UPos> for Pos {
+    type Error = &'static str;
+
+    fn try_from(value: UPos) -> Result<Self, Self::Error> {
+        let Vec2(x,y) = value;
+        match (i32::try_from(x), i32::try_from(y)) {
+            (Ok(x), Ok(y)) => Ok(Pos::new(x,y)),
+            (Err(_), _) => Err("Invalid UPos. x doesn't fit in an i32"),
+            (_, Err(_)) => Err("Invalid UPos. y doesn't fit in an i32"),
+        }
     }
 }
 
-impl Add for Pos {
-    type Output = Pos;
+impl UPos {
+    /// None if we'd step below 0. Takes a `Dir` or a `Dir8`.
+    pub fn checked_add_dir(&self, dir: impl Into<Pos>) -> Option<UPos> {
+        self.checked_offset(dir.into())
+    }
 
-    fn add(self, rhs: Self) -> Self::Output {
-        Self (self.0 + rhs.0, self.1 + rhs.1)
+    pub fn checked_sub(&self, rhs: UPos) -> Option<UPos> {
+        Some(UPos::new(self.0.checked_sub(rhs.0)?, self.1.checked_sub(rhs.1)?))
     }
-}
 
-impl Mul<i32> for Pos {
-    type Output = Pos;
+    /// Stops at 0 instead of going below it
+    pub fn saturating_sub(&self, rhs: UPos) -> UPos {
+        UPos::new(self.0.saturating_sub(rhs.0), self.1.saturating_sub(rhs.1))
+    }
 
-    fn mul(self, rhs: i32) -> Self::Output {
-        Self (self.0 * rhs, self.1 * rhs)
+    fn checked_offset(&self, offset: Pos) -> Option<UPos> {
+        let x = self.0.checked_add_signed(offset.0 as isize)?;
+        let y = self.1.checked_add_signed(offset.1 as isize)?;
+        Some(UPos::new(x,y))
     }
-}
 
-impl From<UPos> for Pos {
-    fn from(value: UPos) -> Self {
-        Pos(value.0 as i32, value.1 as i32)
+    /// Move by `offset`, as long as we stay inside a grid of `dims`
+    /// (row_count, width).
+    pub fn offset_within(&self, offset: Pos, dims: UPos) -> Option<UPos> {
+        let upos = self.checked_offset(offset)?;
+        (upos.0 < dims.0 && upos.1 < dims.1).then_some(upos)
     }
 }
 
-pub struct PosIter {
-    pos: Pos,
-    width: usize,
-}
+impl TryFrom<Pos> for UPos {
+    type Error = &'static str;
 
-impl PosIter {
-    pub fn new(width: usize) -> Self {
-        Self {
-            pos: Pos::new(0, 0),
-            width
+    fn try_from(value: Pos) -> Result<Self, Self::Error> {
+        let Vec2(x,y) = value;
+        if x < 0 {
+            Err("Invalid Pos. x should be greater or equal than 0")
+        } else if y < 0 {
+            Err("Invalid Pos. y should be greater or equal than 0")
+        } else {
+            Ok(UPos::new(x as usize,y as usize))
         }
     }
 }
 
-impl Iterator for PosIter {
+impl Pos {
+    /// `self`, `self + step`, `self + step * 2`, ...
+    ///
+    /// Never ends on its own (unless it overflows), so pair it with
+    /// `take_while` or similar.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is zero, that ray would never move.
+    pub fn ray(self, step: impl Into<Pos>) -> Ray {
+        let step = step.into();
+        assert!(step != Pos::new(0,0), "a ray needs a non zero step");
+
+        Ray { next: Some(self), step }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ray {
+    next: Option<Pos>,
+    step: Pos,
+}
+
+impl Iterator for Ray {
     type Item = Pos;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos.1 < self.width as i32 {
-            let ret = Some(self.pos);
+        let curr = self.next?;
+        self.next = curr.0.checked_add(self.step.0)
+            .zip(curr.1.checked_add(self.step.1))
+            .map(|(x,y)| Pos::new(x,y));
 
-            self.pos.1 += 1;
-            ret
-        } else {
-            self.pos.1 = 0;
-            self.pos.0 += 1;
+        Some(curr)
+    }
+}
 
-            let ret = Some(self.pos);
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
 
-            self.pos.1 += 1;
-            ret
+/// The cells a line from `a` to `b` goes through (Bresenham), both ends included.
+/// Neighbouring cells always touch, at least diagonally.
+pub fn line_between(a: Pos, b: Pos) -> Vec<Pos> {
+    let Vec2(dx, dy) = b - a;
+    let step = Pos::new(dx.signum(), dy.signum());
+    let (dx, dy) = (dx.abs(), -dy.abs());
+
+    let mut line = vec![a];
+    let mut curr = a;
+    let mut err = dx + dy;
+    while curr != b {
+        let err2 = err * 2;
+        if err2 >= dy {
+            err += dy;
+            curr.0 += step.0;
+        }
+        if err2 <= dx {
+            err += dx;
+            curr.1 += step.1;
         }
+        line.push(curr);
     }
-}
 
-#[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq)]
-pub struct UPos(pub usize, pub usize);
+    line
+}
 
-impl UPos {
-    pub fn new(x:usize, y:usize) -> Self {
-        Self (x,y)
+/// The points with integer coordinates that lie exactly on the segment
+/// from `a` to `b`, both ends included.
+pub fn lattice_points(a: Pos, b: Pos) -> Vec<Pos> {
+    let offset = b - a;
+    let steps = gcd(offset.0.unsigned_abs(), offset.1.unsigned_abs());
+    if steps == 0 {
+        return vec![a];
     }
+    let step = Pos::new(offset.0 / steps as i32, offset.1 / steps as i32);
+
+    a.ray(step).take(steps as usize + 1).collect()
 }
 
-impl Sub for UPos {
-    type Output = UPos;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Row by row, like the cells of a Matrix are stored
+    RowMajor,
+    /// Column by column
+    ColumnMajor,
+}
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self (self.0 - rhs.0, self.1 - rhs.1)
-    }
+/// Every position of a `row_count`x`width` grid.
+#[derive(Debug, Clone)]
+pub struct UPosIter {
+    row_count: usize,
+    width: usize,
+    order: Order,
+    // the positions left are the ones with index in front..back
+    front: usize,
+    back: usize,
 }
 
-impl Add for UPos {
-    type Output = UPos;
+impl UPosIter {
+    pub fn new(row_count: usize, width: usize) -> Self {
+        Self::with_order(row_count, width, Order::RowMajor)
+    }
 
-    fn add(self, rhs: Self) -> Self::Output {
-        Self (self.0 + rhs.0, self.1 + rhs.1)
+    pub fn column_major(row_count: usize, width: usize) -> Self {
+        Self::with_order(row_count, width, Order::ColumnMajor)
+    }
+
+    pub fn with_order(row_count: usize, width: usize, order: Order) -> Self {
+        Self {
+            row_count,
+            width,
+            order,
+            front: 0,
+            back: row_count * width,
+        }
+    }
+
+    fn upos_at(&self, index: usize) -> UPos {
+        match self.order {
+            Order::RowMajor => UPos::new(index / self.width, index % self.width),
+            Order::ColumnMajor => UPos::new(index % self.row_count, index / self.row_count),
+        }
     }
 }
 
-impl Mul<usize> for UPos {
-    type Output = UPos;
+impl Iterator for UPosIter {
+    type Item = UPos;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            let ret = self.upos_at(self.front);
+
+            self.front += 1;
+            Some(ret)
+        } else {
+            None
+        }
+    }
 
-    fn mul(self, rhs: usize) -> Self::Output {
-        Self (self.0 * rhs, self.1 * rhs)
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
     }
 }
 
-impl TryFrom<Pos> for UPos {
-    type Error = &'static str;
+impl DoubleEndedIterator for UPosIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.back -= 1;
 
-    fn try_from(value: Pos) -> Result<Self, Self::Error> {
-        let Pos(x,y) = value;
-        if x < 0 {
-            Err("Invalid Pos. x should be greater or equal than 0")
-        } else if y < 0 {
-            Err("Invalid Pos. y should be greater or equal than 0")
+            Some(self.upos_at(self.back))
         } else {
-            Ok(UPos(x as usize,y as usize))
+            None
         }
     }
 }
 
-pub struct UPosIter {
-    pos: UPos,
-    width: usize,
+impl ExactSizeIterator for UPosIter {}
+
+/// Grids big enough to overflow this wouldn't fit in memory anyway.
+fn to_pos(upos: UPos) -> Pos {
+    Pos::try_from(upos).expect("grid coordinates fit in an i32")
 }
 
-impl UPosIter {
-    pub fn new(width: usize) -> Self {
-        Self {
-            pos: UPos::new(0, 0),
-            width
-        }
+/// Same as UPosIter, giving Pos instead.
+#[derive(Debug, Clone)]
+pub struct PosIter {
+    inner: UPosIter,
+}
+
+impl PosIter {
+    pub fn new(row_count: usize, width: usize) -> Self {
+        Self { inner: UPosIter::new(row_count, width) }
+    }
+
+    pub fn column_major(row_count: usize, width: usize) -> Self {
+        Self { inner: UPosIter::column_major(row_count, width) }
+    }
+
+    pub fn with_order(row_count: usize, width: usize, order: Order) -> Self {
+        Self { inner: UPosIter::with_order(row_count, width, order) }
     }
 }
 
-impl Iterator for UPosIter {
-    type Item = UPos;
+impl Iterator for PosIter {
+    type Item = Pos;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos.1 < self.width {
-            let ret = Some(self.pos);
-
-            self.pos.1 += 1;
-            ret
-        } else {
-            self.pos.1 = 0;
-            self.pos.0 += 1;
+        self.inner.next().map(to_pos)
+    }
 
-            let ret = Some(self.pos);
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
 
-            self.pos.1 += 1;
-            ret
-        }
+impl DoubleEndedIterator for PosIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(to_pos)
     }
 }
 
+impl ExactSizeIterator for PosIter {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Dir {
     Up,
     Down,
@@ -166,35 +279,103 @@ pub enum Dir {
 }
 
 impl Dir {
+    /// In the same order as `Mask::cardinal`
+    pub fn all() -> [Dir; 4] {
+        [Dir::Up, Dir::Down, Dir::Left, Dir::Right]
+    }
+
+    /// Position in `Dir::all()`, handy for indexing per direction state.
+    pub fn index(&self) -> usize {
+        match self {
+            Dir::Up => 0,
+            Dir::Down => 1,
+            Dir::Left => 2,
+            Dir::Right => 3,
+        }
+    }
+
     pub fn opposite(&self, rhs: &Self) -> bool {
-        matches!((self, rhs), 
-            (Dir::Up, Dir::Down) |
-            (Dir::Down, Dir::Up) |
-            (Dir::Left, Dir::Right) |
-            (Dir::Right, Dir::Left))
+        self.reverse() == *rhs
+    }
+
+    pub fn reverse(&self) -> Self {
+        match self {
+            Dir::Up => Dir::Down,
+            Dir::Down => Dir::Up,
+            Dir::Left => Dir::Right,
+            Dir::Right => Dir::Left,
+        }
+    }
+
+    /// 90° counter-clockwise
+    pub fn turn_left(&self) -> Self {
+        match self {
+            Dir::Up => Dir::Left,
+            Dir::Left => Dir::Down,
+            Dir::Down => Dir::Right,
+            Dir::Right => Dir::Up,
+        }
+    }
+
+    /// 90° clockwise
+    pub fn turn_right(&self) -> Self {
+        match self {
+            Dir::Up => Dir::Right,
+            Dir::Right => Dir::Down,
+            Dir::Down => Dir::Left,
+            Dir::Left => Dir::Up,
+        }
+    }
+
+    /// Arrows as they show up in the puzzles: '^', 'v', '<' and '>'
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '^' => Some(Dir::Up),
+            'v' => Some(Dir::Down),
+            '<' => Some(Dir::Left),
+            '>' => Some(Dir::Right),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(&self) -> char {
+        match self {
+            Dir::Up => '^',
+            Dir::Down => 'v',
+            Dir::Left => '<',
+            Dir::Right => '>',
+        }
     }
 }
 
 impl From<Dir> for Pos {
     fn from(value: Dir) -> Self {
         match value {
-            Dir::Up => Pos(-1,0),
-            Dir::Down => Pos(1,0),
-            Dir::Left => Pos(0,-1),
-            Dir::Right => Pos(0,1),
+            Dir::Up => Pos::new(-1,0),
+            Dir::Down => Pos::new(1,0),
+            Dir::Left => Pos::new(0,-1),
+            Dir::Right => Pos::new(0,1),
         }
     }
 }
 
+impl Add<Dir> for Pos {
+    type Output = Pos;
+
+    fn add(self, rhs: Dir) -> Self::Output {
+        self + Pos::from(rhs)
+    }
+}
+
 impl TryFrom<Pos> for Dir {
     type Error = &'static str;
 
     fn try_from(value: Pos) -> Result<Self, Self::Error> {
         match value {
-            Pos(-1,0) => Ok(Dir::Up),
-            Pos(1,0) => Ok(Dir::Down),
-            Pos(0,-1) => Ok(Dir::Left),
-            Pos(0,1) => Ok(Dir::Right),
+            Vec2(-1,0) => Ok(Dir::Up),
+            Vec2(1,0) => Ok(Dir::Down),
+            Vec2(0,-1) => Ok(Dir::Left),
+            Vec2(0,1) => Ok(Dir::Right),
             _ => Err("Invalid Pos"),
         }
     }
@@ -210,3 +391,220 @@ impl Display for Dir {
         }
     }
 }
+
+/// Dir plus the diagonals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dir8 {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Dir8 {
+    /// Clockwise, starting from Up
+    pub fn all() -> [Dir8; 8] {
+        [
+            Dir8::Up, Dir8::UpRight, Dir8::Right, Dir8::DownRight,
+            Dir8::Down, Dir8::DownLeft, Dir8::Left, Dir8::UpLeft,
+        ]
+    }
+
+    /// Position in `Dir8::all()`
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        self.index() % 2 == 1
+    }
+
+    pub fn reverse(&self) -> Self {
+        Self::all()[(self.index() + 4) % 8]
+    }
+
+    /// 45° counter-clockwise
+    pub fn turn_left(&self) -> Self {
+        Self::all()[(self.index() + 7) % 8]
+    }
+
+    /// 45° clockwise
+    pub fn turn_right(&self) -> Self {
+        Self::all()[(self.index() + 1) % 8]
+    }
+}
+
+impl From<Dir> for Dir8 {
+    fn from(value: Dir) -> Self {
+        match value {
+            Dir::Up => Dir8::Up,
+            Dir::Down => Dir8::Down,
+            Dir::Left => Dir8::Left,
+            Dir::Right => Dir8::Right,
+        }
+    }
+}
+
+impl TryFrom<Dir8> for Dir {
+    type Error = &'static str;
+
+    fn try_from(value: Dir8) -> Result<Self, Self::Error> {
+        match value {
+            Dir8::Up => Ok(Dir::Up),
+            Dir8::Down => Ok(Dir::Down),
+            Dir8::Left => Ok(Dir::Left),
+            Dir8::Right => Ok(Dir::Right),
+            _ => Err("Diagonals aren't a Dir"),
+        }
+    }
+}
+
+impl From<Dir8> for Pos {
+    fn from(value: Dir8) -> Self {
+        match value {
+            Dir8::Up => Pos::new(-1,0),
+            Dir8::UpRight => Pos::new(-1,1),
+            Dir8::Right => Pos::new(0,1),
+            Dir8::DownRight => Pos::new(1,1),
+            Dir8::Down => Pos::new(1,0),
+            Dir8::DownLeft => Pos::new(1,-1),
+            Dir8::Left => Pos::new(0,-1),
+            Dir8::UpLeft => Pos::new(-1,-1),
+        }
+    }
+}
+
+impl Add<Dir8> for Pos {
+    type Output = Pos;
+
+    fn add(self, rhs: Dir8) -> Self::Output {
+        self + Pos::from(rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_turns() {
+        for dir in Dir::all() {
+            assert_eq!(dir.turn_left().turn_right(), dir);
+            assert_eq!(dir.turn_right().turn_right(), dir.reverse());
+            assert!(dir.opposite(&dir.reverse()));
+            assert_eq!(Dir::from_char(dir.to_char()), Some(dir));
+            assert_eq!(Dir::all()[dir.index()], dir);
+        }
+
+        assert_eq!(Pos::new(3,3) + Dir::Up, Pos::new(2,3));
+        assert_eq!(UPos::new(3,3).checked_add_dir(Dir::Left), Some(UPos::new(3,2)));
+    }
+
+    #[test]
+    fn test_dir8() {
+        assert_eq!(Dir8::Up.turn_right(), Dir8::UpRight);
+        assert_eq!(Dir8::Up.turn_left(), Dir8::UpLeft);
+        assert_eq!(Dir8::DownLeft.reverse(), Dir8::UpRight);
+        assert_eq!(Dir8::all().iter().filter(|dir| dir.is_diagonal()).count(), 4);
+
+        assert_eq!(Pos::new(0,0) + Dir8::DownLeft, Pos::new(1,-1));
+        assert_eq!(UPos::new(1,1).checked_add_dir(Dir8::UpLeft), Some(UPos::new(0,0)));
+        assert_eq!(UPos::new(0,1).checked_add_dir(Dir8::UpLeft), None);
+        assert_eq!(Dir::try_from(Dir8::from(Dir::Right)), Ok(Dir::Right));
+    }
+
+    #[test]
+    fn test_upos_iter_terminates() {
+        let positions: Vec<UPos> = UPosIter::new(2, 3).collect();
+
+        assert_eq!(positions, vec![
+            UPos::new(0,0), UPos::new(0,1), UPos::new(0,2),
+            UPos::new(1,0), UPos::new(1,1), UPos::new(1,2),
+        ]);
+    }
+
+    #[test]
+    fn test_column_major_and_reverse() {
+        let positions: Vec<UPos> = UPosIter::column_major(2, 2).collect();
+        assert_eq!(positions, vec![UPos::new(0,0), UPos::new(1,0), UPos::new(0,1), UPos::new(1,1)]);
+
+        let positions: Vec<Pos> = PosIter::new(2, 2).rev().collect();
+        assert_eq!(positions, vec![Pos::new(1,1), Pos::new(1,0), Pos::new(0,1), Pos::new(0,0)]);
+    }
+
+    #[test]
+    fn test_exact_size_from_both_ends() {
+        let mut positions = UPosIter::new(3, 3);
+        assert_eq!(positions.len(), 9);
+
+        positions.next();
+        positions.next_back();
+        assert_eq!(positions.len(), 7);
+        assert_eq!(positions.next_back(), Some(UPos::new(2,1)));
+    }
+
+    #[test]
+    fn test_checked_upos() {
+        assert_eq!(UPos::new(0,0).checked_sub(UPos::new(1,0)), None);
+        assert_eq!(UPos::new(3,2).checked_sub(UPos::new(1,2)), Some(UPos::new(2,0)));
+        assert_eq!(UPos::new(0,3).saturating_sub(UPos::new(1,1)), UPos::new(0,2));
+
+        assert_eq!(UPos::new(0,0).checked_add_dir(Dir::Up), None);
+        assert_eq!(UPos::new(0,0).checked_add_dir(Dir::Right), Some(UPos::new(0,1)));
+
+        assert_eq!(UPos::new(1,1).offset_within(Pos::new(1,-1), UPos::new(3,3)), Some(UPos::new(2,0)));
+        assert_eq!(UPos::new(2,2).offset_within(Pos::new(0,1), UPos::new(3,3)), None);
+        assert_eq!(UPos::new(0,0).offset_within(Pos::new(-1,0), UPos::new(3,3)), None);
+    }
+
+    #[test]
+    fn test_conversions_dont_truncate() {
+        assert!(Pos::try_from(UPos::new(1 << 40, 0)).is_err());
+        assert!(UPos::try_from(Pos::new(0,-1)).is_err());
+        assert_eq!(Pos::try_from(UPos::new(2,3)), Ok(Pos::new(2,3)));
+    }
+
+    #[test]
+    fn test_distances() {
+        assert_eq!(Pos::new(-1,2).manhattan(&Pos::new(2,-2)), 7);
+        assert_eq!(Pos::new(-1,2).chebyshev(&Pos::new(2,-2)), 4);
+        assert_eq!(Pos::new(-1,2).euclid_sq(&Pos::new(2,-2)), 25);
+        assert_eq!(Pos::new(i32::MIN,0).manhattan(&Pos::new(i32::MAX,0)), u32::MAX as u64);
+
+        assert_eq!(UPos::new(4,0).manhattan(&UPos::new(1,4)), 7);
+        assert_eq!(UPos::new(4,0).chebyshev(&UPos::new(1,4)), 4);
+        assert_eq!(UPos::new(4,0).euclid_sq(&UPos::new(1,4)), 25);
+    }
+
+    #[test]
+    fn test_ray() {
+        let ray: Vec<Pos> = Pos::new(1,1).ray(Dir::Right).take(3).collect();
+        assert_eq!(ray, vec![Pos::new(1,1), Pos::new(1,2), Pos::new(1,3)]);
+
+        assert_eq!(Pos::new(0, i32::MAX - 1).ray(Pos::new(0,1)).count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "non zero step")]
+    fn test_zero_ray() {
+        let _ = Pos::new(1,1).ray(Pos::new(0,0));
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(line_between(Pos::new(0,0), Pos::new(2,4)), vec![
+            Pos::new(0,0), Pos::new(1,1), Pos::new(1,2), Pos::new(2,3), Pos::new(2,4),
+        ]);
+        assert_eq!(line_between(Pos::new(3,3), Pos::new(3,3)), vec![Pos::new(3,3)]);
+        assert_eq!(line_between(Pos::new(2,0), Pos::new(0,0)), vec![Pos::new(2,0), Pos::new(1,0), Pos::new(0,0)]);
+
+        assert_eq!(lattice_points(Pos::new(0,0), Pos::new(2,4)), vec![Pos::new(0,0), Pos::new(1,2), Pos::new(2,4)]);
+        assert_eq!(lattice_points(Pos::new(0,0), Pos::new(2,3)), vec![Pos::new(0,0), Pos::new(2,3)]);
+        assert_eq!(lattice_points(Pos::new(1,1), Pos::new(1,1)), vec![Pos::new(1,1)]);
+    }
+}
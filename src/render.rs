@@ -0,0 +1,210 @@
+use std::{collections::HashSet, fmt::Display};
+use crate::{matrix::Matrix, position::UPos};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    /// ANSI foreground code
+    fn code(&self) -> u8 {
+        match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+        }
+    }
+}
+
+/// What gets drawn on a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    symbol: String,
+    color: Option<Color>,
+}
+
+impl Glyph {
+    pub fn new(symbol: impl Display) -> Self {
+        Self { symbol: symbol.to_string(), color: None }
+    }
+
+    pub fn colored(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    fn width(&self) -> usize {
+        self.symbol.chars().count()
+    }
+
+    fn write_padded(&self, f: &mut std::fmt::Formatter<'_>, width: usize) -> std::fmt::Result {
+        let padding = width.saturating_sub(self.width());
+        write!(f, "{:padding$}", "")?;
+
+        match self.color {
+            Some(color) => write!(f, "\x1b[{}m{}\x1b[0m", color.code(), self.symbol),
+            None => write!(f, "{}", self.symbol),
+        }
+    }
+}
+
+impl From<char> for Glyph {
+    fn from(value: char) -> Self {
+        Glyph::new(value)
+    }
+}
+
+impl From<&str> for Glyph {
+    fn from(value: &str) -> Self {
+        Glyph::new(value)
+    }
+}
+
+type GlyphFn<'a, T> = Box<dyn Fn(&T) -> Glyph + 'a>;
+
+struct Layer {
+    cells: HashSet<UPos>,
+    glyph: Glyph,
+}
+
+/// Draws a Matrix with some layers on top of it.
+///
+/// Layers are drawn in the order they were added, so the last one wins.
+/// Labels go on top of every layer.
+pub struct Renderer<'a, T> {
+    base: &'a Matrix<T>,
+    glyphs: Option<GlyphFn<'a, T>>,
+    layers: Vec<Layer>,
+    labels: Vec<(UPos, Glyph)>,
+    auto_width: bool,
+}
+
+impl<'a, T: Display> Renderer<'a, T> {
+    pub fn new(base: &'a Matrix<T>) -> Self {
+        Self {
+            base,
+            glyphs: None,
+            layers: Vec::new(),
+            labels: Vec::new(),
+            auto_width: false,
+        }
+    }
+
+    /// Decide how the base cells look, instead of using their Display.
+    pub fn with_glyphs(mut self, glyphs: impl Fn(&T) -> Glyph + 'a) -> Self {
+        self.glyphs = Some(Box::new(glyphs));
+        self
+    }
+
+    pub fn with_path(self, path: &[UPos], glyph: impl Into<Glyph>) -> Self {
+        self.with_positions(path.iter().copied(), glyph)
+    }
+
+    pub fn with_positions(mut self, positions: impl IntoIterator<Item = UPos>, glyph: impl Into<Glyph>) -> Self {
+        self.layers.push(Layer {
+            cells: positions.into_iter().collect(),
+            glyph: glyph.into(),
+        });
+        self
+    }
+
+    pub fn with_label(mut self, upos: UPos, glyph: impl Into<Glyph>) -> Self {
+        self.labels.push((upos, glyph.into()));
+        self
+    }
+
+    /// Pad every cell to the widest one and separate them with a space.
+    /// Useful for numeric matrices like cost maps.
+    pub fn auto_width(mut self) -> Self {
+        self.auto_width = true;
+        self
+    }
+
+    fn glyph_at(&self, val: &T, upos: UPos) -> Glyph {
+        if let Some((_upos, glyph)) = self.labels.iter().rev().find(|(label, _)| *label == upos) {
+            return glyph.clone();
+        }
+
+        if let Some(layer) = self.layers.iter().rev().find(|layer| layer.cells.contains(&upos)) {
+            return layer.glyph.clone();
+        }
+
+        match &self.glyphs {
+            Some(glyphs) => glyphs(val),
+            None => Glyph::new(val),
+        }
+    }
+}
+
+impl<T: Display> Display for Renderer<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cells: Vec<Glyph> = self.base.give_upos()
+            .map(|(val, upos)| self.glyph_at(val, upos))
+            .collect();
+
+        let width = if self.auto_width {
+            cells.iter().map(Glyph::width).max().unwrap_or(0)
+        } else {
+            0
+        };
+
+        for row in cells.chunks(self.base.width()) {
+            for (i, glyph) in row.iter().enumerate() {
+                if self.auto_width && i > 0 {
+                    write!(f, " ")?;
+                }
+                glyph.write_padded(f, width)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers() {
+        let matrix = Matrix::with_capacity(2, 3, '.');
+
+        let render = Renderer::new(&matrix)
+            .with_path(&[UPos::new(0,0), UPos::new(0,1), UPos::new(1,1)], 'O')
+            .with_positions([UPos::new(1,1), UPos::new(1,2)], '#')
+            .with_label(UPos::new(0,0), 'S');
+
+        assert_eq!(render.to_string(), "SO.\n.##\n");
+    }
+
+    #[test]
+    fn test_auto_width() {
+        let matrix = Matrix::new(vec![1, 200, 30, 4], 2);
+
+        let render = Renderer::new(&matrix).auto_width();
+
+        assert_eq!(render.to_string(), "  1 200\n 30   4\n");
+    }
+
+    #[test]
+    fn test_colors() {
+        let matrix = Matrix::with_capacity(1, 2, '.');
+
+        let render = Renderer::new(&matrix)
+            .with_label(UPos::new(0,1), Glyph::new('@').colored(Color::Red));
+
+        assert_eq!(render.to_string(), ".\x1b[31m@\x1b[0m\n");
+    }
+}
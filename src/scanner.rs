@@ -1,91 +1,82 @@
-pub struct Scanner {
+use std::{any::type_name, collections::VecDeque, fmt::Display, io, ops::RangeInclusive, str::Utf8Error};
+
+/// Walks over a borrowed string without copying it.
+///
+/// The cursor is a byte offset, and everything that gives back text
+/// gives back slices of the input.
+pub struct Scanner<'a> {
     cursor: usize,
-    characters: Vec<char>,
-    enabled: bool,
+    input: &'a str,
 }
 
-impl Scanner {
-    pub fn new(string: &str) -> Self {
+impl<'a> Scanner<'a> {
+    pub fn new(string: &'a str) -> Self {
         Self {
             cursor: 0,
-            characters: string.chars().collect(),
-            enabled: true,
+            input: string,
         }
     }
 
-    /// get current cursor
-    pub fn cursor(&self) -> usize {
-        self.cursor
-    }
-
-    pub fn enabled(&self) -> bool {
-        self.enabled
-    }
-
-    pub fn r#do(&mut self) {
-        self.enabled = true;
+    /// Fails if the bytes aren't valid UTF-8.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Utf8Error> {
+        Ok(Self::new(std::str::from_utf8(bytes)?))
     }
 
-    pub fn dont(&mut self) {
-        self.enabled = false;
+    /// get current cursor, in bytes
+    pub fn cursor(&self) -> usize {
+        self.cursor
     }
 
     /// get next char without advancing the cursor
-    pub fn peek(&self) -> Option<&char> {
-        self.characters.get(self.cursor)
+    pub fn peek(&self) -> Option<char> {
+        let byte = *self.input.as_bytes().get(self.cursor)?;
+
+        if byte.is_ascii() {
+            Some(byte as char)
+        } else {
+            self.input[self.cursor..].chars().next()
+        }
     }
 
     /// return true if cursor is at the end
     pub fn is_done(&self) -> bool {
-        self.cursor == self.characters.len()
+        self.cursor == self.input.len()
     }
 
     /// get next char (if available) and advance the cursor
-    pub fn pop(&mut self) -> Option<&char> {
-        match self.characters.get(self.cursor) {
-            Some(character) => {
-                self.cursor += 1;
+    pub fn pop(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.cursor += character.len_utf8();
 
-                Some(character)
-            }
-            None => None,
-        }
+        Some(character)
     }
 
     /// pop n times and advance the cursor.
     /// if we reach the end, we still return something
-    pub fn popn(&mut self, times: usize) -> Option<String> {
-        let mut times = times;
+    pub fn popn(&mut self, times: usize) -> Option<&'a str> {
         let start = self.cursor;
-        while times > 0 {
-            let res = self.pop();
-            if res.is_none() {
+        for _ in 0..times {
+            if self.pop().is_none() {
                 break;
             }
-
-            times -= 1;
         }
-        let end = self.cursor;
-        if start == end {
+
+        if start == self.cursor {
             None
         } else {
-            Some(self.characters[start..end].iter().collect())
+            Some(&self.input[start..self.cursor])
         }
     }
 
     /// return true if chars[cursor] == target and advance the cursor 
     pub fn take(&mut self, target: &char) -> bool {
-        match self.characters.get(self.cursor) {
-            Some(character) => {
-                if target == character {
-                    self.cursor += 1;
+        match self.peek() {
+            Some(character) if *target == character => {
+                self.cursor += character.len_utf8();
 
-                    true
-                } else {
-                    false
-                }
+                true
             }
-            None => false,
+            _ => false,
         }
     }
 
@@ -94,57 +85,53 @@ impl Scanner {
         &mut self,
         callback: impl FnOnce(&char) -> Option<T>,
     ) -> Option<T> {
-        match self.characters.get(self.cursor) {
-            Some(input) => match callback(input) {
-                Some(output) => {
-                    self.cursor += 1;
+        let input = self.peek()?;
+        let output = callback(&input)?;
+        self.cursor += input.len_utf8();
 
-                    Some(output)
-                }
-                None => None,
-            }
-            None => None,
-        }
+        Some(output)
     }
 
     /// Scan a slice for a pattern and perform an Action.
+    ///
+    /// If a `Require` isn't met the cursor goes back to where it was.
     pub fn scan<T>(
         &mut self,
         callback: impl Fn(&str) -> Option<Action<T>>,
     ) -> Result<Option<T>, Error> {
-        let mut sequence = String::new();
+        let start = self.cursor;
         let mut require = false;
         let mut request = None;
 
         loop {
-            match self.characters.get(self.cursor) {
+            match self.peek() {
                 Some(target) => {
-                    sequence.push(*target);
+                    let sequence = &self.input[start..self.cursor + target.len_utf8()];
 
-                    match callback(&sequence) {
+                    match callback(sequence) {
                         Some(Action::Return(result)) => {
-                            self.cursor += 1;
+                            self.cursor += target.len_utf8();
 
                             break Ok(Some(result))
                         },
                         Some(Action::Request(result)) => {
-                            self.cursor += 1;
+                            self.cursor += target.len_utf8();
                             require = false;
                             request = Some(result);
                         },
                         Some(Action::Require) => {
-                            self.cursor += 1;
+                            self.cursor += target.len_utf8();
                             require = true;
                         },
                         None => if require {
-                            break Err(Error::Character(self.cursor))
+                            break Err(self.rewind_with("the rest of the pattern", start))
                         } else {
                             break Ok(request)
                         },
                     }
                 },
                 None => if require {
-                    break Err(Error::EndOfLine)
+                    break Err(self.rewind_with("the rest of the pattern", start))
                 } else {
                     break Ok(request)
                 },
@@ -152,74 +139,310 @@ impl Scanner {
         }
     }
 
-    pub fn try_u32(&mut self) -> Option<u32> {
-        match self.peek() {
-            Some(char) => {
-                if char.is_ascii_digit() {
-                    let mut ret = char.to_digit(10).unwrap();
-                    self.pop();
+    /// Move the cursor to the earliest match of any of `patterns`.
+    /// When two matches start at the same place the longest one wins.
+    ///
+    /// If there's none the cursor goes to the end.
+    pub fn find_next(&mut self, patterns: &Patterns) -> Option<Match> {
+        match patterns.find_at(self.input, self.cursor) {
+            Some(found) => {
+                self.cursor = found.start;
+                Some(found)
+            },
+            None => {
+                self.cursor = self.input.len();
+                None
+            },
+        }
+    }
 
-                    while let Some(char) = self.peek() {
-                        if char.is_ascii_digit() {
+    /// The text of `found`, moving the cursor past it.
+    /// None unless the cursor is where it starts, like right after `find_next`.
+    pub fn take_match(&mut self, found: &Match) -> Option<&'a str> {
+        if self.cursor != found.start {
+            return None;
+        }
 
-                            ret = ret*10 + char.to_digit(10).unwrap();
-                            self.pop();
-                        } else {
-                            break;
-                        }
-                    }
+        let text = self.input.get(found.start..found.end)?;
+        self.cursor = found.end;
 
-                    Some(ret)
-                } else {
-                    None
-                }
-            },
-            None => None,
+        Some(text)
+    }
+
+    /// Every match from the cursor on, without overlaps and
+    /// without moving the cursor.
+    pub fn find_all<'p>(&self, patterns: &'p Patterns) -> impl Iterator<Item = Match> + use<'a, 'p> {
+        let input = self.input;
+        let mut from = self.cursor;
+
+        std::iter::from_fn(move || {
+            let found = patterns.find_at(input, from)?;
+            from = found.end;
+            Some(found)
+        })
+    }
+
+    /// Error for the cursor, then go back to `start`.
+    fn rewind_with(&mut self, expected: &str, start: usize) -> Error {
+        let error = self.unexpected(expected);
+        self.cursor = start;
+        error
+    }
+
+    /// Remember where the cursor is, to `restore` it later.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.cursor)
+    }
+
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.cursor = checkpoint.0;
+    }
+
+    /// Run `parser`, putting the cursor back where it was if it fails.
+    ///
+    /// ```
+    /// # use advent_of_code2024::scanner::Scanner;
+    /// let mut scanner = Scanner::new("mul(2,x)");
+    /// let product = scanner.attempt(|s| {
+    ///     s.expect("mul(")?;
+    ///     let left = s.try_int::<i32>()?;
+    ///     s.expect(",")?;
+    ///     let right = s.try_int::<i32>()?;
+    ///     s.expect(")")?;
+    ///     Ok::<_, advent_of_code2024::scanner::Error>(left * right)
+    /// });
+    ///
+    /// assert!(product.is_err());
+    /// assert_eq!(scanner.cursor(), 0);
+    /// ```
+    pub fn attempt<R: Outcome>(&mut self, parser: impl FnOnce(&mut Self) -> R) -> R {
+        let checkpoint = self.checkpoint();
+
+        let result = parser(self);
+        if !result.succeeded() {
+            self.restore(checkpoint);
         }
+
+        result
+    }
+
+    pub fn try_u32(&mut self) -> Option<u32> {
+        self.try_int().ok()
     }
 
     pub fn try_i32(&mut self) -> Option<i32> {
-        match self.peek() {
-            Some('-') => {
-                self.pop();
-                
-                let mut ret;
-                if let Some(first) = self.peek() {
-                    if first.is_ascii_digit() {
-                        ret = first.to_digit(10).unwrap();
-                        self.pop();
-                    } else {
-                        return None;
-                    }
-                } else {
-                    return None;
-                }
-                
-                while let Some(char) = self.peek() {
-                    if char.is_ascii_digit() {
-                        ret = ret*10 + char.to_digit(10).unwrap();
-                        self.pop();
-                    } else {
-                        break;
-                    }
-                }
+        self.try_int().ok()
+    }
 
-                Some(-(ret as i32))
-            },
-            Some(char) if char.is_ascii_digit() => {
-                let mut ret = 0;
-                while let Some(char) = self.peek() {
-                    if char.is_ascii_digit() {
-                        ret = ret*10 + char.to_digit(10).unwrap();
-                        self.pop();
-                    } else {
-                        break;
-                    }
-                }
+    /// Parse a decimal integer. Signed types take an optional '-' or '+',
+    /// unsigned ones no sign at all (`take(&'+')` first to allow one).
+    ///
+    /// On error the cursor goes back to where it was.
+    pub fn try_int<T: PrimInt>(&mut self) -> Result<T, Error> {
+        let start = self.cursor;
 
-                Some(ret as i32)
-            },
-            _ => None,
+        let negative = match self.peek() {
+            Some('-') if T::SIGNED => { self.pop(); true },
+            Some('+') if T::SIGNED => { self.pop(); false },
+            _ => false,
+        };
+
+        let result = self.digits(10, negative, start, type_name::<T>());
+        if result.is_err() {
+            self.cursor = start;
+        }
+
+        result
+    }
+
+    /// Parse a run of `count` decimal digits, without a sign. A longer run
+    /// is an error rather than a number cut short, "1234" isn't 123.
+    ///
+    /// On error the cursor goes back to where it was.
+    pub fn try_digits<T: PrimInt>(&mut self, count: RangeInclusive<usize>) -> Result<T, Error> {
+        let start = self.cursor;
+
+        let run = self.take_while(char::is_ascii_digit).len();
+        self.cursor = start;
+        if !count.contains(&run) {
+            let expected = format!("{} to {} digits", count.start(), count.end());
+            return Err(self.error_at(ErrorKind::Character, start, expected));
+        }
+
+        let result = self.digits(10, false, start, type_name::<T>());
+        if result.is_err() {
+            self.cursor = start;
+        }
+
+        result
+    }
+
+    /// Parse hexadecimal digits, with or without a leading "0x".
+    ///
+    /// On error the cursor goes back to where it was.
+    pub fn try_hex<T: PrimInt>(&mut self) -> Result<T, Error> {
+        let start = self.cursor;
+
+        if !self.try_pattern("0x") {
+            self.cursor = start;
+        }
+
+        let result = self.digits(16, false, start, type_name::<T>());
+        if result.is_err() {
+            self.cursor = start;
+        }
+
+        result
+    }
+
+    /// Accumulate digits in `radix`, subtracting when `negative`
+    /// so `T::MIN` can be parsed too.
+    fn digits<T: PrimInt>(
+        &mut self,
+        radix: u32,
+        negative: bool,
+        start: usize,
+        type_name: &str,
+    ) -> Result<T, Error> {
+        let article = if type_name.starts_with('i') { "an" } else { "a" };
+        let expected = || format!("{article} {type_name}");
+
+        let mut ret = T::ZERO;
+        let mut any = false;
+
+        while let Some(digit) = self.peek().and_then(|char| char.to_digit(radix)) {
+            let digit = T::from_digit(digit);
+            let next = ret.checked_mul(T::from_digit(radix)).and_then(|ret| {
+                if negative { ret.checked_sub(digit) } else { ret.checked_add(digit) }
+            });
+
+            ret = next.ok_or_else(|| self.error_at(ErrorKind::Overflow, start, expected()))?;
+            any = true;
+            self.pop();
+        }
+
+        if any {
+            Ok(ret)
+        } else {
+            Err(self.unexpected(expected()))
+        }
+    }
+
+    /// Parse something like "-12", "3.5" or "1e-3".
+    ///
+    /// On error the cursor goes back to where it was.
+    pub fn try_float(&mut self) -> Result<f64, Error> {
+        let start = self.cursor;
+
+        if !self.take(&'-') {
+            self.take(&'+');
+        }
+        let mut digits = self.skip_digits();
+        if self.take(&'.') {
+            digits += self.skip_digits();
+        }
+
+        if digits == 0 {
+            let error = self.unexpected("a float");
+            self.cursor = start;
+            return Err(error);
+        }
+
+        // Only an exponent if there are digits after the 'e'
+        let before_exponent = self.cursor;
+        if self.take(&'e') || self.take(&'E') {
+            if !self.take(&'-') {
+                self.take(&'+');
+            }
+            if self.skip_digits() == 0 {
+                self.cursor = before_exponent;
+            }
+        }
+
+        Ok(self.input[start..self.cursor].parse().expect("only valid floats get here"))
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let mut count = 0;
+        while self.transform(|char| char.is_ascii_digit().then_some(())).is_some() {
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Match a template like "p={},{} v={},{}", where every `{}` is a value
+    /// of the matching type in `T`:
+    ///
+    /// ```
+    /// # use advent_of_code2024::scanner::Scanner;
+    /// let mut scanner = Scanner::new("p=0,4 v=3,-3");
+    /// let robot = scanner.extract::<(usize, usize, i32, i32)>("p={},{} v={},{}");
+    /// assert_eq!(robot.unwrap(), (0, 4, 3, -3));
+    /// ```
+    ///
+    /// On error the cursor goes back to where it was.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the amount of `{}` isn't the size of `T`.
+    pub fn extract<T: Extract>(&mut self, template: &str) -> Result<T, Error> {
+        let literals: Vec<&str> = template.split("{}").collect();
+        assert_eq!(literals.len() - 1, T::PLACEHOLDERS, "template {template:?} doesn't match the type");
+
+        let start = self.cursor;
+        let result = T::extract(self, &literals);
+        if result.is_err() {
+            self.cursor = start;
+        }
+
+        result
+    }
+
+    /// `extract` over and over until the input runs out,
+    /// skipping whitespace (newlines included) between matches.
+    pub fn extract_all<T: Extract>(&mut self, template: &str) -> Result<Vec<T>, Error> {
+        let mut values = Vec::new();
+
+        self.skip_ws();
+        while !self.is_done() {
+            values.push(self.extract(template)?);
+            self.skip_ws();
+        }
+
+        Ok(values)
+    }
+
+    /// Used by `Extract` for each placeholder.
+    fn placeholder<V: Scan>(&mut self, index: usize, count: usize) -> Result<V, Error> {
+        V::scan(self).map_err(|mut error| {
+            error.expected = format!("{} for placeholder {} of {count}", error.expected, index + 1);
+            error
+        })
+    }
+
+    /// Error for whatever is under the cursor.
+    fn unexpected(&self, expected: impl Into<String>) -> Error {
+        let kind = match self.peek() {
+            Some(_) => ErrorKind::Character,
+            None => ErrorKind::EndOfLine,
+        };
+
+        self.error_at(kind, self.cursor, expected)
+    }
+
+    fn error_at(&self, kind: ErrorKind, offset: usize, expected: impl Into<String>) -> Error {
+        let line_start = self.input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.input[offset..].find('\n').map_or(self.input.len(), |i| offset + i);
+
+        Error {
+            kind,
+            offset,
+            line: self.input[..line_start].bytes().filter(|byte| *byte == b'\n').count() + 1,
+            column: self.input[line_start..offset].chars().count() + 1,
+            expected: expected.into(),
+            found: self.input[offset..].chars().next(),
+            snippet: self.input[line_start..line_end].to_string(),
         }
     }
 
@@ -232,14 +455,424 @@ impl Scanner {
 
         true
     }
+
+    /// Skip spaces, tabs and newlines. Returns how many were skipped.
+    pub fn skip_ws(&mut self) -> usize {
+        self.take_while(|char| char.is_whitespace()).chars().count()
+    }
+
+    /// Like `try_pattern`, but the cursor only moves if the whole
+    /// pattern is there, and the error says where it stopped matching.
+    pub fn expect(&mut self, pattern: &str) -> Result<(), Error> {
+        let start = self.cursor;
+
+        for val in pattern.chars() {
+            if !self.take(&val) {
+                let error = self.unexpected(format!("{pattern:?}"));
+                self.cursor = start;
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Zero or more `item`s separated by `sep`, like "0,3,5,4".
+    ///
+    /// A `sep` has to be followed by an item. On error the cursor goes back
+    /// to where it was.
+    pub fn sep_by<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Scanner<'a>) -> Result<T, Error>,
+        sep: &str,
+    ) -> Result<Vec<T>, Error> {
+        let start = self.cursor;
+
+        let mut items = match item(self) {
+            Ok(first) => vec![first],
+            Err(_) => {
+                self.cursor = start;
+                return Ok(Vec::new());
+            }
+        };
+
+        while self.expect(sep).is_ok() {
+            match item(self) {
+                Ok(next) => items.push(next),
+                Err(error) => {
+                    self.cursor = start;
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Everything up to `target` (or the end), leaving the cursor on `target`.
+    pub fn until(&mut self, target: char) -> &'a str {
+        self.take_while(|char| *char != target)
+    }
+
+    /// Pop chars while `pred` holds.
+    pub fn take_while(&mut self, pred: impl Fn(&char) -> bool) -> &'a str {
+        let start = self.cursor;
+        while self.transform(|char| pred(char).then_some(())).is_some() {}
+
+        &self.input[start..self.cursor]
+    }
+
+    /// A letter or '_', followed by letters, digits or '_'. Like "x00" or "ntg".
+    pub fn ident(&mut self) -> Result<&'a str, Error> {
+        match self.peek() {
+            Some(char) if char.is_alphabetic() || char == '_' => {
+                Ok(self.take_while(|char| char.is_alphanumeric() || *char == '_'))
+            },
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    /// The rest of the current line. The '\n' gets consumed but isn't returned.
+    pub fn line(&mut self) -> Option<&'a str> {
+        if self.is_done() {
+            return None;
+        }
+
+        let line = self.until('\n');
+        self.take(&'\n');
+
+        Some(line)
+    }
 }
 
-#[derive(Debug)]
-pub enum Error {
-    Character(usize),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Something else was there
+    Character,
+    /// The input ended too soon
     EndOfLine,
+    /// The number there doesn't fit in the type
+    Overflow,
+}
+
+/// Where and why scanning failed, printed like a compiler diagnostic:
+///
+/// ```text
+/// 2:5: expected a u32, found 'x'
+///   2 | p=1,x v=3,-3
+///     |     ^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: usize,
+    line: usize,
+    column: usize,
+    expected: String,
+    found: Option<char>,
+    snippet: String,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Bytes from the start of the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Starts at 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Starts at 1
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: expected {}, found ", self.line, self.column, self.expected)?;
+        match (self.kind, self.found) {
+            (ErrorKind::Overflow, _) => writeln!(f, "a number that doesn't fit")?,
+            (_, Some('\n')) => writeln!(f, "the end of the line")?,
+            (_, Some(char)) => writeln!(f, "{char:?}")?,
+            (_, None) => writeln!(f, "the end of the input")?,
+        }
+
+        let gutter = self.line.to_string().len();
+        writeln!(f, " {} | {}", self.line, self.snippet)?;
+        write!(f, " {:gutter$} | {:>column$}", "", "^", column = self.column)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for io::Error {
+    fn from(value: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, value)
+    }
+}
+
+/// Where `Scanner::find_next` found something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// Index in the slice given to `Patterns::new`
+    pub pattern: usize,
+    /// Byte offsets, `end` excluded
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Match {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+const ROOT: usize = 0;
+
+/// A few patterns to look for at once (Aho–Corasick).
+///
+/// The automaton is built once, so keep it around between searches.
+#[derive(Debug, Clone)]
+pub struct Patterns {
+    lengths: Vec<usize>,
+    /// `next[state][byte]`, with the failure links already followed
+    next: Vec<[usize; 256]>,
+    /// Patterns that end on each state, longest first
+    outputs: Vec<Vec<usize>>,
+}
+
+impl Patterns {
+    /// # Panics
+    ///
+    /// Panics if a pattern is empty.
+    pub fn new(patterns: &[&str]) -> Self {
+        let mut next = vec![[ROOT; 256]];
+        let mut outputs = vec![Vec::new()];
+        // Which edges are actual trie edges and not a way back to the root
+        let mut trie = vec![[false; 256]];
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            assert!(!pattern.is_empty(), "patterns can't be empty");
+
+            let mut state = ROOT;
+            for byte in pattern.bytes() {
+                let byte = byte as usize;
+                if !trie[state][byte] {
+                    next.push([ROOT; 256]);
+                    outputs.push(Vec::new());
+                    trie.push([false; 256]);
+
+                    next[state][byte] = next.len() - 1;
+                    trie[state][byte] = true;
+                }
+                state = next[state][byte];
+            }
+            outputs[state].push(index);
+        }
+
+        // Breadth first, so the failure state is always done before we need it.
+        let mut fail = vec![ROOT; next.len()];
+        let mut queue: VecDeque<usize> = (0..256)
+            .filter(|byte| trie[ROOT][*byte])
+            .map(|byte| next[ROOT][byte])
+            .collect();
+
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state]].clone();
+            outputs[state].extend(inherited);
+
+            for byte in 0..256 {
+                if trie[state][byte] {
+                    let child = next[state][byte];
+                    fail[child] = next[fail[state]][byte];
+                    queue.push_back(child);
+                } else {
+                    next[state][byte] = next[fail[state]][byte];
+                }
+            }
+        }
+
+        let lengths: Vec<usize> = patterns.iter().map(|pattern| pattern.len()).collect();
+        for output in &mut outputs {
+            output.sort_by_key(|index| std::cmp::Reverse(lengths[*index]));
+        }
+
+        Self { lengths, next, outputs }
+    }
+
+    /// Leftmost match starting at `from` or later, longest on ties.
+    fn find_at(&self, haystack: &str, from: usize) -> Option<Match> {
+        let longest = self.lengths.iter().copied().max()?;
+        let mut best: Option<Match> = None;
+        let mut state = ROOT;
+
+        for (i, byte) in haystack.bytes().enumerate().skip(from) {
+            // Nothing that ends from here on can start before `best`
+            if best.is_some_and(|best| i >= best.start + longest) {
+                break;
+            }
+
+            state = self.next[state][byte as usize];
+            if let Some(&pattern) = self.outputs[state].first() {
+                let found = Match { pattern, start: i + 1 - self.lengths[pattern], end: i + 1 };
+                if best.is_none_or(|best| (found.start, best.len()) < (best.start, found.len())) {
+                    best = Some(found);
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// A cursor position from `Scanner::checkpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+/// What a `Scanner::attempt` parser can give back.
+pub trait Outcome {
+    fn succeeded(&self) -> bool;
+}
+
+impl<T> Outcome for Option<T> {
+    fn succeeded(&self) -> bool {
+        self.is_some()
+    }
+}
+
+impl<T, E> Outcome for Result<T, E> {
+    fn succeeded(&self) -> bool {
+        self.is_ok()
+    }
+}
+
+/// Values that can go in an `extract` placeholder.
+pub trait Scan: Sized {
+    fn scan(scanner: &mut Scanner<'_>) -> Result<Self, Error>;
+}
+
+impl Scan for f64 {
+    fn scan(scanner: &mut Scanner<'_>) -> Result<Self, Error> {
+        scanner.try_float()
+    }
+}
+
+/// An identifier, see `Scanner::ident`
+impl Scan for String {
+    fn scan(scanner: &mut Scanner<'_>) -> Result<Self, Error> {
+        scanner.ident().map(str::to_string)
+    }
+}
+
+impl Scan for char {
+    fn scan(scanner: &mut Scanner<'_>) -> Result<Self, Error> {
+        match scanner.peek() {
+            Some(char) => {
+                scanner.pop();
+                Ok(char)
+            },
+            None => Err(scanner.unexpected("a character")),
+        }
+    }
+}
+
+/// Tuples of `Scan` values, filled in by `Scanner::extract`.
+pub trait Extract: Sized {
+    const PLACEHOLDERS: usize;
+
+    /// `literals` has the text before, between and after the placeholders.
+    fn extract(scanner: &mut Scanner<'_>, literals: &[&str]) -> Result<Self, Error>;
+}
+
+macro_rules! impl_extract {
+    ($count:literal => $($value:ident $index:tt),+) => {
+        impl<$($value: Scan),+> Extract for ($($value,)+) {
+            const PLACEHOLDERS: usize = $count;
+
+            fn extract(scanner: &mut Scanner<'_>, literals: &[&str]) -> Result<Self, Error> {
+                let values = ($({
+                    scanner.expect(literals[$index])?;
+                    scanner.placeholder::<$value>($index, $count)?
+                },)+);
+                scanner.expect(literals[$count])?;
+
+                Ok(values)
+            }
+        }
+    };
+}
+
+impl_extract!(1 => A 0);
+impl_extract!(2 => A 0, B 1);
+impl_extract!(3 => A 0, B 1, C 2);
+impl_extract!(4 => A 0, B 1, C 2, D 3);
+impl_extract!(5 => A 0, B 1, C 2, D 3, E 4);
+impl_extract!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+impl_extract!(7 => A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_extract!(8 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Integer types `Scanner::try_int` can parse into.
+pub trait PrimInt: Copy {
+    const ZERO: Self;
+    const SIGNED: bool;
+
+    /// `digit` is always below 16
+    fn from_digit(digit: u32) -> Self;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_prim_int {
+    ($($int:ty),*) => {
+        $(
+            impl PrimInt for $int {
+                const ZERO: Self = 0;
+                const SIGNED: bool = <$int>::MIN != 0;
+
+                fn from_digit(digit: u32) -> Self {
+                    digit as Self
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$int>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$int>::checked_sub(self, rhs)
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$int>::checked_mul(self, rhs)
+                }
+            }
+
+            impl Scan for $int {
+                fn scan(scanner: &mut Scanner<'_>) -> Result<Self, Error> {
+                    scanner.try_int()
+                }
+            }
+        )*
+    };
+}
+
+impl_prim_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
 pub enum Action<T> {
     /// If next iteration returns None,
     /// return T without advancing the cursor.
@@ -257,6 +890,11 @@ pub enum Action<T> {
 mod tests {
     use super::*;
 
+    fn failure<T: std::fmt::Debug>(result: Result<T, Error>) -> (ErrorKind, usize) {
+        let error = result.unwrap_err();
+        (error.kind(), error.offset())
+    }
+
     #[test]
     fn test_parse_u32() {
         let input = "12345";
@@ -286,4 +924,247 @@ mod tests {
 
         assert_eq!(result, Some(-13));
     }
+
+    #[test]
+    fn test_try_int() {
+        let mut scanner = Scanner::new("+42,-9223372036854775808,18446744073709551616");
+
+        assert_eq!(scanner.try_int::<i8>(), Ok(42));
+        scanner.pop();
+        assert_eq!(scanner.try_int::<i64>(), Ok(i64::MIN));
+        scanner.pop();
+
+        let start = scanner.cursor();
+        assert_eq!(failure(scanner.try_int::<u64>()), (ErrorKind::Overflow, start));
+        assert_eq!(scanner.cursor(), start);
+        assert_eq!(scanner.try_int::<u128>(), Ok(u64::MAX as u128 + 1));
+        assert_eq!(failure(scanner.try_int::<i32>()), (ErrorKind::EndOfLine, 45));
+    }
+
+    #[test]
+    fn fail_try_int_sign() {
+        let mut scanner = Scanner::new("-3");
+
+        assert_eq!(failure(scanner.try_int::<u32>()), (ErrorKind::Character, 0));
+        assert_eq!(scanner.try_int::<i8>(), Ok(-3));
+
+        let mut scanner = Scanner::new("-x");
+        assert_eq!(failure(scanner.try_int::<i32>()), (ErrorKind::Character, 1));
+        assert_eq!(scanner.cursor(), 0);
+
+        // Unsigned types only take a '+' if asked to
+        let mut scanner = Scanner::new("+7");
+        assert_eq!(failure(scanner.try_int::<u32>()), (ErrorKind::Character, 0));
+        assert!(scanner.take(&'+'));
+        assert_eq!(scanner.try_int::<u32>(), Ok(7));
+    }
+
+    #[test]
+    fn test_try_digits() {
+        let mut scanner = Scanner::new("007,1234,+5,");
+
+        assert_eq!(scanner.try_digits::<u32>(1..=3), Ok(7));
+        scanner.pop();
+        assert_eq!(failure(scanner.try_digits::<u32>(1..=3)), (ErrorKind::Character, 4));
+        assert_eq!(scanner.cursor(), 4);
+        assert_eq!(scanner.try_digits::<u32>(1..=4), Ok(1234));
+        scanner.pop();
+        assert_eq!(failure(scanner.try_digits::<u32>(1..=3)), (ErrorKind::Character, 9));
+        scanner.popn(2);
+        assert_eq!(failure(scanner.try_digits::<u32>(1..=3)), (ErrorKind::Character, 11));
+    }
+
+    #[test]
+    fn test_try_float_and_hex() {
+        let mut scanner = Scanner::new("-1.5e3 .25 7e 0xff ag");
+
+        assert_eq!(scanner.try_float(), Ok(-1500.0));
+        scanner.pop();
+        assert_eq!(scanner.try_float(), Ok(0.25));
+        scanner.pop();
+        assert_eq!(scanner.try_float(), Ok(7.0));
+        assert_eq!(scanner.peek(), Some('e'));
+        scanner.popn(2);
+
+        assert_eq!(scanner.try_hex::<u8>(), Ok(255));
+        scanner.pop();
+        assert_eq!(scanner.try_hex::<u32>(), Ok(0xa));
+        assert_eq!(failure(scanner.try_hex::<u32>()), (ErrorKind::Character, 20));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let mut scanner = Scanner::new("Program: 0,3,5\n  x00 AND y_1 -> z00\nlast");
+
+        assert_eq!(scanner.until(':'), "Program");
+        assert!(scanner.expect(": ").is_ok());
+        assert_eq!(scanner.sep_by(|s| s.try_int::<u8>(), ","), Ok(vec![0, 3, 5]));
+        assert_eq!(scanner.skip_ws(), 3);
+
+        assert_eq!(scanner.ident(), Ok("x00"));
+        scanner.skip_ws();
+        assert_eq!(scanner.take_while(|c| c.is_uppercase()), "AND");
+        scanner.skip_ws();
+        assert_eq!(scanner.ident(), Ok("y_1"));
+        assert_eq!(failure(scanner.expect(" => ")), (ErrorKind::Character, 29));
+        assert_eq!(scanner.line(), Some(" -> z00"));
+        assert_eq!(scanner.line(), Some("last"));
+        assert_eq!(scanner.line(), None);
+    }
+
+    #[test]
+    fn fail_sep_by() {
+        let mut scanner = Scanner::new("1,2,x");
+
+        assert_eq!(failure(scanner.sep_by(|s| s.try_int::<u8>(), ",")), (ErrorKind::Character, 4));
+        assert_eq!(scanner.cursor(), 0);
+
+        let mut scanner = Scanner::new("x");
+        assert_eq!(scanner.sep_by(|s| s.try_int::<u8>(), ","), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_diagnostic() {
+        let mut scanner = Scanner::new("p=0,4 v=3,-3\np=1,x v=3,-3");
+        scanner.line();
+        scanner.expect("p=1,").unwrap();
+
+        let error = scanner.try_int::<u32>().unwrap_err();
+
+        assert_eq!((error.line(), error.column()), (2, 5));
+        assert_eq!(error.to_string(), "\
+2:5: expected a u32, found 'x'
+ 2 | p=1,x v=3,-3
+   |     ^");
+
+        let error = Scanner::new("ab").expect("abc").unwrap_err();
+        assert!(error.to_string().starts_with("1:3: expected \"abc\", found the end of the input"));
+    }
+
+    #[test]
+    fn test_extract() {
+        let input = "\
+Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Prize: X=12748, Y=12176
+";
+        let template = "Button A: X+{}, Y+{}\nButton B: X+{}, Y+{}\nPrize: X={}, Y={}";
+
+        let machines = Scanner::new(input).extract_all::<(i64, i64, i64, i64, i64, i64)>(template);
+
+        assert_eq!(machines.unwrap(), vec![
+            (94, 34, 22, 67, 8400, 5400),
+            (26, 66, 67, 21, 12748, 12176),
+        ]);
+    }
+
+    #[test]
+    fn fail_extract() {
+        let mut scanner = Scanner::new("p=0,4 v=3,x");
+
+        let error = scanner.extract::<(usize, usize, i32, i32)>("p={},{} v={},{}").unwrap_err();
+
+        assert_eq!(error.expected(), "an i32 for placeholder 4 of 4");
+        assert_eq!(error.column(), 11);
+        assert_eq!(scanner.cursor(), 0);
+
+        let error = scanner.extract::<(usize, char)>("p={}.{}").unwrap_err();
+        assert_eq!(error.expected(), "\".\"");
+    }
+
+    #[test]
+    fn test_borrowed_slices() {
+        let input = String::from("añb: 12\nrest");
+        let mut scanner = Scanner::from_bytes(input.as_bytes()).unwrap();
+
+        let key = scanner.until(':');
+        assert_eq!(key, "añb");
+        assert_eq!(scanner.cursor(), 4);
+        assert!(std::ptr::eq(key.as_ptr(), input.as_ptr()));
+
+        assert_eq!(scanner.popn(2), Some(": "));
+        assert_eq!(scanner.try_int::<u8>(), Ok(12));
+
+        let error = Scanner::new("ñx").expect("ñy").unwrap_err();
+        assert_eq!((error.offset(), error.column()), (2, 2));
+
+        assert!(Scanner::from_bytes(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let mut scanner = Scanner::new("abc");
+
+        let checkpoint = scanner.checkpoint();
+        scanner.popn(2);
+        scanner.restore(checkpoint);
+        assert_eq!(scanner.peek(), Some('a'));
+
+        assert_eq!(scanner.attempt(|s| s.pop().filter(|c| *c == 'x')), None);
+        assert_eq!(scanner.cursor(), 0);
+        assert_eq!(scanner.attempt(|s| s.popn(2)), Some("ab"));
+        assert_eq!(scanner.cursor(), 2);
+    }
+
+    #[test]
+    fn test_scan_rewinds() {
+        let mut scanner = Scanner::new("mumul(");
+        let mul = |symbol: &str| match symbol {
+            "m" | "mu" | "mul" => Some(Action::Require),
+            "mul(" => Some(Action::Return(())),
+            _ => None,
+        };
+
+        assert!(scanner.scan(mul).is_err());
+        assert_eq!(scanner.cursor(), 0);
+
+        scanner.popn(2);
+        assert_eq!(scanner.scan(mul), Ok(Some(())));
+    }
+
+    #[test]
+    fn test_find_next() {
+        let patterns = Patterns::new(&["mul(", "do()", "don't()"]);
+        let mut scanner = Scanner::new("xmul(2,4)&don't()_mul(5,5)do()");
+
+        let found = scanner.find_next(&patterns).unwrap();
+        assert_eq!((found.pattern, found.start), (0, 1));
+        assert_eq!(scanner.cursor(), 1);
+
+        scanner.pop();
+        assert_eq!(scanner.find_next(&patterns).map(|found| found.pattern), Some(2));
+
+        let rest: Vec<usize> = scanner.find_all(&patterns).map(|found| found.pattern).collect();
+        assert_eq!(rest, vec![2, 0, 1]);
+        assert_eq!(scanner.cursor(), 10);
+
+        let found = scanner.find_next(&patterns).unwrap();
+        assert_eq!(scanner.take_match(&found), Some("don't()"));
+        assert_eq!(scanner.take_match(&found), None);
+        assert_eq!(scanner.cursor(), 17);
+
+        scanner.popn(20);
+        assert_eq!(scanner.find_next(&patterns), None);
+        assert!(scanner.is_done());
+    }
+
+    #[test]
+    fn test_find_leftmost_longest() {
+        // "bc" ends first, but "abcd" starts earlier
+        let patterns = Patterns::new(&["bc", "abcd", "ab"]);
+
+        let found = Scanner::new("xabcd").find_next(&patterns).unwrap();
+        assert_eq!((found.pattern, found.start, found.len()), (1, 1, 4));
+
+        let patterns = Patterns::new(&["she", "he", "hers"]);
+        let found: Vec<(usize, usize)> = Scanner::new("ushers")
+            .find_all(&patterns)
+            .map(|found| (found.pattern, found.start))
+            .collect();
+        assert_eq!(found, vec![(0, 1)]);
+    }
 }
@@ -1,118 +1,854 @@
 use std::{
-    sync::{mpsc, Arc, Mutex},
+    any::Any,
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Display,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc,
+        Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak,
+    },
     thread,
+    time::{Duration, Instant},
 };
+use crate::matrix::Matrix;
 
-pub struct ThreadPool<T> {
-    workers: Vec<Worker<T>>,
-    sender: Option<mpsc::Sender<Job<T>>>,
-    my_receiver: Option<mpsc::Receiver<(usize,T)>>,
+/// `T` is what jobs sent with `execute` give back. Jobs sent with `submit`
+/// can return anything, so it defaults to `()` for pools that only use those.
+///
+/// Every worker has its own queue of jobs and steals from the others when
+/// it runs dry. Jobs sent from outside the pool go through a shared injector
+/// queue, jobs spawned by a job go to the worker's own queue.
+pub struct ThreadPool<T = ()> {
+    shared: Arc<Shared>,
+    my_receiver: Option<mpsc::Receiver<(usize, JobResult<T>)>>,
+    returns: mpsc::Sender<(usize, JobResult<T>)>,
+    /// `execute` jobs whose result hasn't been collected yet
+    pending: AtomicUsize,
 }
 
-type Job<T> = Box<dyn FnOnce() -> T + Send + 'static>;
+/// Gets the pool and the id of the worker running it.
+type Job = ScopedJob<'static>;
+
+/// A job that borrows for `'scope`, see `Scope::spawn`.
+type ScopedJob<'scope> = Box<dyn FnOnce(&Shared, usize) + Send + 'scope>;
+
+type Logger = Box<dyn Fn(&Event) + Send + Sync + 'static>;
+
+/// What the workers have in common.
+struct Shared {
+    /// Jobs sent from outside the pool
+    injector: Mutex<VecDeque<Job>>,
+    /// One per worker. The owner works at the back, thieves take from the front.
+    deques: Vec<Mutex<VecDeque<Job>>>,
+    /// Jobs sitting in any of the queues
+    queued: AtomicUsize,
+    shutdown: AtomicBool,
+    /// Idle workers wait on `wake`
+    sleep: Mutex<()>,
+    sleeping: AtomicUsize,
+    wake: Condvar,
+    /// Workers waiting on a job (see `help_while`) wait on `helped`,
+    /// also with `sleep` held.
+    waiting: AtomicUsize,
+    helped: Condvar,
+    /// Join handles by worker id, so a dying worker can put its replacement in.
+    workers: Mutex<Vec<Option<thread::JoinHandle<()>>>>,
+    stats: Vec<Counters>,
+    logger: Logger,
+}
+
+/// Most jobs a worker moves from the injector to its own queue at once.
+const INJECTOR_BATCH: usize = 32;
+
+/// How many times a worker with nothing to do looks for jobs again before it
+/// sleeps. Sleeping and waking up for every tiny job costs more than the jobs.
+const IDLE_SPINS: usize = 64;
+
+thread_local! {
+    /// The pool and id of the worker running on this thread, if any.
+    static WORKER: RefCell<Option<(Arc<Shared>, usize)>> = const { RefCell::new(None) };
+}
+
+/// Nothing panics while holding these locks, but don't die over it if it does.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn current_worker() -> Option<(Arc<Shared>, usize)> {
+    WORKER.with(|worker| worker.borrow().clone())
+}
+
+impl Shared {
+    /// A logger that panics doesn't get to take a job down with it.
+    fn log(&self, event: Event) {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| (self.logger)(&event)));
+    }
+
+    /// Our id if this thread is one of our workers.
+    fn worker_id(&self) -> Option<usize> {
+        WORKER.with(|worker| match &*worker.borrow() {
+            Some((shared, id)) if std::ptr::eq(Arc::as_ptr(shared), self) => Some(*id),
+            _ => None,
+        })
+    }
+
+    fn push(&self, job: Job) {
+        // Counted first, so nobody takes it out before it's counted
+        self.queued.fetch_add(1, Ordering::SeqCst);
+
+        match self.worker_id() {
+            Some(id) => lock(&self.deques[id]).push_back(job),
+            None => lock(&self.injector).push_back(job),
+        }
+
+        // Either a worker going to sleep sees the job, or we see it sleeping.
+        // Waiting workers only get it when no idle one is around.
+        if self.sleeping.load(Ordering::SeqCst) > 0 {
+            let _sleep = lock(&self.sleep);
+            self.wake.notify_one();
+        } else if self.waiting.load(Ordering::SeqCst) > 0 {
+            let _sleep = lock(&self.sleep);
+            self.helped.notify_one();
+        }
+    }
+
+    /// Something a worker is waiting on may be done, see `help_while`.
+    fn wake_helpers(&self) {
+        if self.waiting.load(Ordering::SeqCst) > 0 {
+            let _sleep = lock(&self.sleep);
+            self.helped.notify_all();
+        }
+    }
+
+    /// Our own newest job, or the oldest ones from outside, or one stolen from another worker.
+    fn find_job(&self, id: usize) -> Option<Job> {
+        let mut job = lock(&self.deques[id]).pop_back();
+
+        if job.is_none() {
+            job = self.take_injected(id);
+        }
+
+        let count = self.deques.len();
+        for other in (1..count).map(|offset| (id + offset) % count) {
+            if job.is_some() {
+                break;
+            }
+            job = lock(&self.deques[other]).pop_front();
+        }
+
+        if job.is_some() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+        }
+        job
+    }
+
+    /// One job from the injector, with a few more moved to our own queue
+    /// so we don't fight over the injector for every tiny job.
+    fn take_injected(&self, id: usize) -> Option<Job> {
+        let mut injector = lock(&self.injector);
+        let job = injector.pop_front()?;
+
+        let batch = (injector.len() / self.deques.len()).min(INJECTOR_BATCH);
+        if batch > 0 {
+            let mut deque = lock(&self.deques[id]);
+            // Oldest at the back, so they still run in the order they came
+            deque.extend(injector.drain(..batch).rev());
+        }
+
+        Some(job)
+    }
+
+    /// Run other jobs while worker `id` waits for `pending` to turn false,
+    /// sleeping when there are none. Whatever turns it false has to call
+    /// `wake_helpers` afterwards.
+    ///
+    /// Whoever waits may have jobs borrowing its stack, so a job unwinding
+    /// out of here is only passed on once the wait is over.
+    fn help_while(&self, id: usize, mut pending: impl FnMut() -> bool) {
+        let mut unwound = None;
+        let mut idle = 0;
+
+        while pending() {
+            let helped = panic::catch_unwind(AssertUnwindSafe(|| match self.find_job(id) {
+                Some(job) => {
+                    job(self, id);
+                    0
+                },
+                None if idle < IDLE_SPINS => {
+                    thread::yield_now();
+                    idle + 1
+                },
+                None => {
+                    self.park(&mut pending);
+                    0
+                },
+            }));
+
+            match helped {
+                Ok(spins) => idle = spins,
+                Err(payload) => { unwound.get_or_insert(payload); },
+            }
+        }
+
+        if let Some(payload) = unwound {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Sleep until a job gets pushed or `pending` may have changed.
+    fn park(&self, pending: &mut impl FnMut() -> bool) {
+        // Checked while holding `sleep`, so neither can slip in before the wait
+        let sleep = lock(&self.sleep);
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        if self.queued.load(Ordering::SeqCst) == 0 && pending() {
+            let _sleep = self.helped.wait(sleep).unwrap_or_else(PoisonError::into_inner);
+        }
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Run a job for worker `id`, counting it before anyone can see the result.
+    fn run<R>(&self, id: usize, function: impl FnOnce() -> R) -> JobResult<R> {
+        self.log(Event::JobStarted { worker: id });
+        let start = Instant::now();
+
+        let result = catch(function);
+
+        let busy = start.elapsed();
+        let counters = &self.stats[id];
+        counters.jobs.fetch_add(1, Ordering::Relaxed);
+        counters.busy_nanos.fetch_add(busy.as_nanos() as u64, Ordering::Relaxed);
+        self.log(Event::JobFinished { worker: id, busy });
+
+        result
+    }
+}
+
+/// Something that happened in the pool, for `ThreadPool::with_logger`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    JobStarted { worker: usize },
+    JobFinished { worker: usize, busy: Duration },
+    /// A worker thread died and was replaced.
+    WorkerRestarted { worker: usize },
+    WorkerStopped { worker: usize },
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::JobStarted { worker } => write!(f, "Worker {worker} got a job; executing."),
+            Event::JobFinished { worker, busy } => write!(f, "Worker {worker} finished a job in {busy:?}."),
+            Event::WorkerRestarted { worker } => write!(f, "Worker {worker} died; starting a new one."),
+            Event::WorkerStopped { worker } => write!(f, "Worker {worker} disconnected; shutting down."),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    jobs: AtomicU64,
+    busy_nanos: AtomicU64,
+}
+
+/// How much one worker did so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkerStats {
+    pub jobs: u64,
+    pub busy: Duration,
+}
+
+pub type JobResult<R> = Result<R, JobPanic>;
+
+/// A job panicked instead of returning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobPanic {
+    pub message: String,
+}
+
+impl JobPanic {
+    fn new(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown panic".to_string(),
+            },
+        };
+
+        Self { message }
+    }
+}
+
+impl Display for JobPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "job panicked: {}", self.message)
+    }
+}
+
+impl std::error::Error for JobPanic {}
+
+/// Run a job, turning a panic into an error.
+fn catch<R>(function: impl FnOnce() -> R) -> JobResult<R> {
+    panic::catch_unwind(AssertUnwindSafe(function)).map_err(JobPanic::new)
+}
+
+/// Goes up once and stays up.
+#[derive(Default)]
+struct Latch {
+    set: AtomicBool,
+    lock: Mutex<()>,
+    done: Condvar,
+}
+
+impl Latch {
+    fn probe(&self) -> bool {
+        self.set.load(Ordering::SeqCst)
+    }
+
+    fn set(&self) {
+        let _lock = lock(&self.lock);
+        self.set.store(true, Ordering::SeqCst);
+        self.done.notify_all();
+    }
+
+    fn wait(&self) {
+        let unset = lock(&self.lock);
+        let _set = self.done.wait_while(unset, |_| !self.probe()).unwrap_or_else(PoisonError::into_inner);
+    }
+
+    /// False if it's still down after `timeout`.
+    fn wait_timeout(&self, timeout: Duration) -> bool {
+        let unset = lock(&self.lock);
+        let _set = self.done.wait_timeout_while(unset, timeout, |_| !self.probe()).unwrap_or_else(PoisonError::into_inner);
+        self.probe()
+    }
+}
+
+/// Where a job leaves its result for whoever waits on it.
+///
+/// Both sides own it, like the packet of a `std::thread::scope` thread,
+/// so however late the job lets go, it never touches the waiter's memory.
+struct Packet<R> {
+    result: Mutex<Option<JobResult<R>>>,
+    latch: Latch,
+}
+
+/// The job's side of a `Packet`.
+struct Promise<R> {
+    packet: Arc<Packet<R>>,
+    /// To wake a worker waiting on the packet. Weak, a queued job
+    /// shouldn't keep its own pool alive.
+    shared: Weak<Shared>,
+}
+
+impl<R> Promise<R> {
+    fn keep(self, result: JobResult<R>) {
+        *lock(&self.packet.result) = Some(result);
+    }
+}
+
+impl<R> Drop for Promise<R> {
+    /// The latch goes up last, once the job is done with the packet.
+    /// A job dropped without running still lets its waiter go.
+    fn drop(&mut self) {
+        self.packet.latch.set();
+
+        if let Some(shared) = self.shared.upgrade() {
+            shared.wake_helpers();
+        }
+    }
+}
+
+/// Waits for the result of one `submit`ted job.
+pub struct JobHandle<R> {
+    packet: Arc<Packet<R>>,
+    shared: Arc<Shared>,
+}
+
+impl<R> JobHandle<R> {
+    /// A handle and the promise its job has to keep.
+    fn new(shared: &Arc<Shared>) -> (Self, Promise<R>) {
+        let packet = Arc::new(Packet { result: Mutex::new(None), latch: Latch::default() });
+        let promise = Promise { packet: Arc::clone(&packet), shared: Arc::downgrade(shared) };
+
+        (Self { packet, shared: Arc::clone(shared) }, promise)
+    }
+
+    /// Block until the job is done. A worker of the same pool runs
+    /// other jobs in the meantime instead.
+    pub fn join(self) -> JobResult<R> {
+        match self.shared.worker_id() {
+            Some(id) => self.shared.help_while(id, || !self.packet.latch.probe()),
+            None => self.packet.latch.wait(),
+        }
+
+        self.take()
+    }
+
+    /// None if the job isn't done after `timeout`.
+    pub fn join_timeout(&self, timeout: Duration) -> Option<JobResult<R>> {
+        self.packet.latch.wait_timeout(timeout).then(|| self.take())
+    }
+
+    fn take(&self) -> JobResult<R> {
+        lock(&self.packet.result).take().unwrap_or_else(|| Err(JobPanic { message: "job was lost".to_string() }))
+    }
+}
+
+/// Jobs spawned in a `Scope` that haven't finished yet.
+#[derive(Default)]
+struct Running {
+    count: Mutex<usize>,
+    done: Condvar,
+}
+
+impl Running {
+    fn start(&self) {
+        *lock(&self.count) += 1;
+    }
+
+    fn finish(&self, shared: &Shared) {
+        let mut count = lock(&self.count);
+        *count -= 1;
+        if *count == 0 {
+            self.done.notify_all();
+            drop(count);
+            shared.wake_helpers();
+        }
+    }
+
+    fn wait(&self, shared: &Shared) {
+        match shared.worker_id() {
+            Some(id) => shared.help_while(id, || *lock(&self.count) > 0),
+            None => {
+                let count = lock(&self.count);
+                let _count = self.done.wait_while(count, |count| *count > 0).unwrap_or_else(PoisonError::into_inner);
+            },
+        }
+    }
+}
+
+/// Calls `Running::finish` when dropped, even while unwinding.
+/// That's the last thing a scoped job does with its scope.
+struct Finish<'a> {
+    running: Arc<Running>,
+    shared: &'a Shared,
+}
+
+impl Drop for Finish<'_> {
+    fn drop(&mut self) {
+        self.running.finish(self.shared);
+    }
+}
+
+/// Spawns jobs that can borrow anything living longer than the scope,
+/// see `ThreadPool::scope`.
+pub struct Scope<'scope, 'env: 'scope> {
+    shared: Arc<Shared>,
+    running: Arc<Running>,
+    _scope: PhantomData<&'scope mut &'scope ()>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope> Scope<'scope, '_> {
+    pub fn spawn<R, F>(&'scope self, function: F) -> ScopedJobHandle<'scope, R>
+    where
+        R: Send + 'scope,
+        F: FnOnce() -> R + Send + 'scope,
+    {
+        let (handle, promise) = JobHandle::new(&self.shared);
+        let running = Arc::clone(&self.running);
+
+        let job: ScopedJob<'scope> = Box::new(move |shared, id| {
+            // Declared first so it's dropped last, once nothing borrowed is left around
+            let _finish = Finish { running, shared };
+
+            promise.keep(shared.run(id, function));
+        });
+
+        // SAFETY: `ThreadPool::scope` doesn't return (or unwind) before every job
+        // called `finish`, and by then the job let go of everything it borrowed.
+        let job: Job = unsafe {
+            std::mem::transmute::<ScopedJob<'scope>, Job>(job)
+        };
+
+        self.running.start();
+        self.shared.push(job);
+
+        ScopedJobHandle { handle, _scope: PhantomData }
+    }
+}
+
+fn in_scope<'env, F, R>(shared: Arc<Shared>, function: F) -> R
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+{
+    let scope = Scope {
+        shared,
+        running: Arc::new(Running::default()),
+        _scope: PhantomData,
+        _env: PhantomData,
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| function(&scope)));
+    scope.running.wait(&scope.shared);
+
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Run `a` and `b`, maybe at the same time, for divide and conquer.
+///
+/// `b` gets spawned from the worker this is called on and `a` runs right away.
+/// Waiting for `b` means running other jobs, so it can nest as deep as needed.
+/// Outside of a pool both simply run here.
+///
+/// A panic in either is passed on once both are done.
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    let Some((shared, _)) = current_worker() else {
+        return (a(), b());
+    };
+
+    let (handle, promise) = JobHandle::new(&shared);
+
+    let job: ScopedJob = Box::new(move |shared, id| {
+        promise.keep(catch(|| shared.run(id, b)).and_then(|result| result));
+    });
+
+    // SAFETY: we don't return (or unwind) before the job's latch is set, which
+    // only happens once it's done with `b`. The result goes through a packet
+    // we both own, so nothing of ours is touched after that.
+    let job: Job = unsafe { std::mem::transmute::<ScopedJob, Job>(job) };
+    shared.push(job);
+
+    let a = catch(a);
+    let b = handle.join();
+
+    match (a, b) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(panic), _) | (_, Err(panic)) => panic::panic_any(panic.message),
+    }
+}
+
+/// A `JobHandle` that can't leave its scope.
+pub struct ScopedJobHandle<'scope, R> {
+    handle: JobHandle<R>,
+    _scope: PhantomData<&'scope ()>,
+}
+
+impl<R> ScopedJobHandle<'_, R> {
+    pub fn join(self) -> JobResult<R> {
+        self.handle.join()
+    }
+}
 
 impl<T: std::marker::Send + 'static> ThreadPool<T> {
     /// Create a new ThreadPool.
     ///
-    /// The size is the number of threads in the pool.
+    /// The size is the number of threads in the pool. It doesn't print anything,
+    /// see `with_logger` for that.
     ///
     /// # Panics
     ///
     /// The `new` function will panic if the size is zero.
     pub fn new(size: usize) -> ThreadPool<T> {
-        assert!(size > 0);
+        Self::with_logger(size, |_| {})
+    }
 
-        let (sender, receiver) = mpsc::channel();
+    /// Same as `new`, with `logger` getting every `Event`.
+    /// `|event| eprintln!("{event}")` is enough to see what's going on.
+    pub fn with_logger(size: usize, logger: impl Fn(&Event) + Send + Sync + 'static) -> ThreadPool<T> {
+        assert!(size > 0);
 
         let (returns, my_receiver) = mpsc::channel();
 
-        let receiver = Arc::new(Mutex::new(receiver));
-
-        let mut workers = Vec::with_capacity(size);
+        let shared = Arc::new(Shared {
+            injector: Mutex::new(VecDeque::new()),
+            deques: (0..size).map(|_| Mutex::new(VecDeque::new())).collect(),
+            queued: AtomicUsize::new(0),
+            shutdown: AtomicBool::new(false),
+            sleep: Mutex::new(()),
+            sleeping: AtomicUsize::new(0),
+            wake: Condvar::new(),
+            waiting: AtomicUsize::new(0),
+            helped: Condvar::new(),
+            workers: Mutex::new((0..size).map(|_| None).collect()),
+            stats: (0..size).map(|_| Counters::default()).collect(),
+            logger: Box::new(logger),
+        });
 
         for id in 0..size {
-            let worker_sender = returns.clone();
-            workers.push(Worker::new(id, Arc::clone(&receiver), worker_sender));
+            spawn_worker(id, Arc::clone(&shared));
         }
 
         ThreadPool {
-            workers,
-            sender: Some(sender),
+            shared,
             my_receiver: Some(my_receiver),
+            returns,
+            pending: AtomicUsize::new(0),
         }
     }
 
+    /// Run `function` on some worker, the result goes to `await_returns`.
     pub fn execute<F>(&self, function: F)
     where
         F: FnOnce() -> T + Send + 'static,
     {
-        let job = Box::new(function);
+        let returns = self.returns.clone();
+        self.pending.fetch_add(1, Ordering::SeqCst);
+
+        self.send(Box::new(move |shared, id| {
+            let _ = returns.send((id, shared.run(id, function)));
+        }));
+    }
+
+    /// Run `function` on some worker and get a handle to its result.
+    pub fn submit<R, F>(&self, function: F) -> JobHandle<R>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let (handle, promise) = JobHandle::new(&self.shared);
+
+        self.send(Box::new(move |shared, id| promise.keep(shared.run(id, function))));
+
+        handle
+    }
+
+    /// Apply `function` to every item, results come back in input order.
+    ///
+    /// Every item gets processed, then the first panic (if any) is returned.
+    pub fn map<I, R, F>(&self, items: I, function: F) -> JobResult<Vec<R>>
+    where
+        I: IntoIterator,
+        I::Item: Send + 'static,
+        R: Send + 'static,
+        F: Fn(I::Item) -> R + Send + Sync + 'static,
+    {
+        let function = Arc::new(function);
+
+        let handles: Vec<JobHandle<R>> = items.into_iter()
+            .map(|item| {
+                let function = Arc::clone(&function);
+                self.submit(move || function(item))
+            })
+            .collect();
 
-        self.sender.as_ref().unwrap().send(job).unwrap();
+        let results: Vec<JobResult<R>> = handles.into_iter().map(JobHandle::join).collect();
+        results.into_iter().collect()
+    }
+
+    /// Run `function` with a `Scope` whose jobs can borrow local data.
+    /// Returns once every spawned job is done.
+    ///
+    /// Spawned jobs can spawn more on the same scope. Inside one of this pool's
+    /// jobs, the waiting worker runs other jobs until then.
+    pub fn scope<'env, F, R>(&self, function: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+    {
+        in_scope(Arc::clone(&self.shared), function)
     }
 
-    pub fn await_returns(&self, amount: usize) -> Vec<(usize,T)> {
+    /// `function(index, chunk)` for every `chunk_size` long chunk of `items`,
+    /// results in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn par_chunks<'a, E, R, F>(&self, items: &'a [E], chunk_size: usize, function: F) -> JobResult<Vec<R>>
+    where
+        E: Sync,
+        R: Send,
+        F: Fn(usize, &'a [E]) -> R + Sync,
+    {
+        assert!(chunk_size > 0, "par_chunks needs a chunk_size of at least 1");
+
+        self.scope(|scope| {
+            let function = &function;
+
+            let handles: Vec<ScopedJobHandle<R>> = items.chunks(chunk_size)
+                .enumerate()
+                .map(|(index, chunk)| scope.spawn(move || function(index, chunk)))
+                .collect();
+
+            handles.into_iter().map(ScopedJobHandle::join).collect()
+        })
+    }
+
+    /// `function` on every item, split in a few chunks per worker.
+    pub fn par_for_each<E, F>(&self, items: &[E], function: F) -> JobResult<()>
+    where
+        E: Sync,
+        F: Fn(&E) + Sync,
+    {
+        let chunk_size = items.len().div_ceil(self.size() * 4).max(1);
+
+        self.par_chunks(items, chunk_size, |_, chunk| chunk.iter().for_each(&function))
+            .map(|_| ())
+    }
+
+    /// `function(row, cells)` for every row of `matrix`, results in row order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `matrix` has a width of zero.
+    pub fn par_rows<'a, E, R, F>(&self, matrix: &'a Matrix<E>, function: F) -> JobResult<Vec<R>>
+    where
+        E: Sync,
+        R: Send,
+        F: Fn(usize, &'a [E]) -> R + Sync,
+    {
+        self.par_chunks(matrix.as_slice(), matrix.width(), function)
+    }
+
+    pub fn size(&self) -> usize {
+        self.shared.deques.len()
+    }
+
+    /// Jobs run and time spent on them, by worker id.
+    pub fn stats(&self) -> Vec<WorkerStats> {
+        self.shared.stats.iter()
+            .map(|counters| WorkerStats {
+                jobs: counters.jobs.load(Ordering::Relaxed),
+                busy: Duration::from_nanos(counters.busy_nanos.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+
+    fn send(&self, job: Job) {
+        self.shared.push(job);
+    }
+
+    /// Results of `execute` jobs as `(worker_id, result)`, in the order they finished.
+    ///
+    /// Doesn't wait for more jobs than were sent.
+    pub fn await_returns(&self, amount: usize) -> Vec<(usize, JobResult<T>)> {
         let mut returns = Vec::new();
-        for _i in 0..amount {
+        for _i in 0..amount.min(self.pending.load(Ordering::SeqCst)) {
             let message = self.my_receiver.as_ref()
                 .unwrap()
                 .recv()
                 .unwrap();
 
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            returns.push(message);
+        }
+        returns
+    }
+
+    /// Same as `await_returns`, but gives up after `timeout` with what it has.
+    pub fn await_returns_timeout(&self, amount: usize, timeout: Duration) -> Vec<(usize, JobResult<T>)> {
+        let deadline = Instant::now() + timeout;
+        let mut returns = Vec::new();
+        for _i in 0..amount.min(self.pending.load(Ordering::SeqCst)) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let Ok(message) = self.my_receiver.as_ref().unwrap().recv_timeout(left) else {
+                break;
+            };
+
+            self.pending.fetch_sub(1, Ordering::SeqCst);
             returns.push(message);
         }
         returns
     }
 }
 
+/// One worker per core.
+impl<T: std::marker::Send + 'static> Default for ThreadPool<T> {
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(4, |count| count.get()))
+    }
+}
+
 impl<T> Drop for ThreadPool<T> {
     fn drop(&mut self) {
-        drop(self.sender.take());
-
-        for worker in &mut self.workers {
-            println!("Shutting down worker {}", worker.id);
+        // Workers finish what's queued before they stop
+        {
+            let _sleep = lock(&self.shared.sleep);
+            self.shared.shutdown.store(true, Ordering::SeqCst);
+            self.shared.wake.notify_all();
+        }
 
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+        let size = self.shared.stats.len();
+        for id in 0..size {
+            // A worker that dies puts its replacement in before it's done,
+            // so keep going until the slot stays empty.
+            loop {
+                let thread = lock(&self.shared.workers)[id].take();
+                match thread {
+                    Some(thread) => { let _ = thread.join(); },
+                    None => break,
+                }
             }
         }
     }
 }
 
-struct Worker<T> {
+/// Lives on the worker's stack. If the thread unwinds past it, a new
+/// worker takes the same id so the pool keeps its size.
+struct Sentinel {
     id: usize,
-    thread: Option<thread::JoinHandle<()>>,
-    _marker: std::marker::PhantomData<T>,
+    shared: Arc<Shared>,
+}
+
+impl Drop for Sentinel {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.shared.log(Event::WorkerRestarted { worker: self.id });
+            spawn_worker(self.id, Arc::clone(&self.shared));
+        }
+    }
 }
 
-impl<T: std::marker::Send + 'static> Worker<T> {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job<T>>>>, sender: mpsc::Sender<(usize, T)>) -> Worker<T> {
-        let thread = thread::spawn(move || loop {
-            let message = receiver
-                .lock()
-                .expect("Poisoned mutex. Killing worker! :D")
-                .recv();
+fn spawn_worker(id: usize, shared: Arc<Shared>) {
+    let sentinel = Sentinel { id, shared: Arc::clone(&shared) };
+    let worker = Arc::clone(&shared);
 
-            match message {
-                Ok(job) => {
-                    println!("Worker {id} got a job; executing.");
+    let thread = thread::spawn(move || {
+        let _sentinel = sentinel;
+        WORKER.with(|current| *current.borrow_mut() = Some((Arc::clone(&worker), id)));
 
-                    let job_res = job();
-                    let _ = sender.send((id, job_res));
-                }
-                Err(_) => {
-                    println!("Worker {id} disconnected; shutting down.");
-                    break;
-                }
+        let mut idle = 0;
+        loop {
+            if let Some(job) = worker.find_job(id) {
+                job(&worker, id);
+                idle = 0;
+                continue;
             }
-        });
 
-        Worker {
-            id,
-            thread: Some(thread),
-            _marker: std::marker::PhantomData
+            if idle < IDLE_SPINS {
+                idle += 1;
+                thread::yield_now();
+                continue;
+            }
+
+            // Checked while holding `sleep`, so a push can't slip in before the wait
+            let sleep = lock(&worker.sleep);
+            worker.sleeping.fetch_add(1, Ordering::SeqCst);
+            if worker.queued.load(Ordering::SeqCst) == 0 && !worker.shutdown.load(Ordering::SeqCst) {
+                let _sleep = worker.wake.wait(sleep).unwrap_or_else(PoisonError::into_inner);
+            }
+            worker.sleeping.fetch_sub(1, Ordering::SeqCst);
+
+            if worker.shutdown.load(Ordering::SeqCst) && worker.queued.load(Ordering::SeqCst) == 0 {
+                worker.log(Event::WorkerStopped { worker: id });
+                break;
+            }
         }
-    }
+
+        WORKER.with(|current| current.borrow_mut().take());
+    });
+
+    lock(&shared.workers)[id] = Some(thread);
 }
 
 #[cfg(test)]
@@ -138,4 +874,430 @@ mod tests {
 
         println!("returns = {returns:?}");
     }
+
+    #[test]
+    fn await_more_than_sent() {
+        let tp = ThreadPool::new(2);
+        tp.execute(|| 1);
+        tp.execute(|| 2);
+
+        let mut returns: Vec<i32> = tp.await_returns(5).into_iter().map(|(_, val)| val.unwrap()).collect();
+        returns.sort();
+
+        assert_eq!(returns, vec![1, 2]);
+        assert!(tp.await_returns(1).is_empty());
+    }
+
+    #[test]
+    fn await_timeout() {
+        let tp = ThreadPool::new(1);
+        tp.execute(|| {
+            thread::sleep(Duration::from_millis(200));
+            1
+        });
+
+        assert!(tp.await_returns_timeout(1, Duration::from_millis(10)).is_empty());
+        assert_eq!(tp.await_returns_timeout(1, Duration::from_secs(5)), vec![(0, Ok(1))]);
+    }
+
+    #[test]
+    fn submit_and_map() {
+        let tp: ThreadPool = ThreadPool::new(4);
+
+        let slow = tp.submit(|| {
+            thread::sleep(Duration::from_millis(50));
+            "slow"
+        });
+        let fast = tp.submit(|| 42_u64);
+
+        assert_eq!(fast.join(), Ok(42));
+        assert_eq!(slow.join(), Ok("slow"));
+
+        // Later items finish first, the order still holds
+        let squares = tp.map(0..8_u64, |val| {
+            thread::sleep(Duration::from_millis(8 - val));
+            val * val
+        });
+        assert_eq!(squares, Ok(vec![0, 1, 4, 9, 16, 25, 36, 49]));
+    }
+
+    #[test]
+    fn panics_are_caught() {
+        let tp = ThreadPool::new(2);
+
+        let broken = tp.submit(|| -> i32 { panic!("day {} broke", 7) });
+        assert_eq!(broken.join(), Err(JobPanic { message: "day 7 broke".to_string() }));
+
+        tp.execute(|| panic!("oops"));
+        tp.execute(|| 5);
+        let mut returns: Vec<JobResult<i32>> = tp.await_returns(2).into_iter().map(|(_, val)| val).collect();
+        returns.sort_by_key(|val| val.is_ok());
+        assert_eq!(returns, vec![Err(JobPanic { message: "oops".to_string() }), Ok(5)]);
+
+        let doubled = tp.map(0..4, |val| if val == 2 { panic!("two") } else { val * 2 });
+        assert_eq!(doubled.unwrap_err().message, "two");
+    }
+
+    #[test]
+    fn dead_workers_are_replaced() {
+        let tp: ThreadPool = ThreadPool::new(1);
+
+        // Not wrapped in `catch`, so this one takes the worker down
+        tp.send(Box::new(|_, _| panic!("worker killer")));
+
+        assert_eq!(tp.submit(|| 1).join(), Ok(1));
+        assert_eq!(tp.map(0..3, |val| val + 1), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn logging_and_stats() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&events);
+        let tp: ThreadPool = ThreadPool::with_logger(2, move |event| log.lock().unwrap().push(*event));
+
+        let sum: u64 = tp.map(0..10_u64, |val| {
+            thread::sleep(Duration::from_millis(1));
+            val
+        }).unwrap().iter().sum();
+        assert_eq!(sum, 45);
+
+        let stats = tp.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.iter().map(|worker| worker.jobs).sum::<u64>(), 10);
+        assert!(stats.iter().map(|worker| worker.busy).sum::<Duration>() >= Duration::from_millis(10));
+
+        drop(tp);
+
+        let events = events.lock().unwrap();
+        let count = |wanted: fn(&Event) -> bool| events.iter().filter(|event| wanted(event)).count();
+        assert_eq!(count(|event| matches!(event, Event::JobStarted { .. })), 10);
+        assert_eq!(count(|event| matches!(event, Event::JobFinished { .. })), 10);
+        assert_eq!(count(|event| matches!(event, Event::WorkerStopped { .. })), 2);
+    }
+
+    #[test]
+    fn scoped_borrows() {
+        let tp: ThreadPool = ThreadPool::new(3);
+        let mut values: Vec<u64> = (1..=10).collect();
+        let offset = 100;
+
+        tp.scope(|scope| {
+            for chunk in values.chunks_mut(3) {
+                scope.spawn(|| chunk.iter_mut().for_each(|val| *val += offset));
+            }
+        });
+        assert_eq!(values, (101..=110).collect::<Vec<u64>>());
+
+        let sums = tp.par_chunks(&values, 4, |index, chunk| (index, chunk.iter().sum::<u64>()));
+        assert_eq!(sums, Ok(vec![(0, 410), (1, 426), (2, 219)]));
+
+        let total = AtomicU64::new(0);
+        tp.par_for_each(&values, |val| { total.fetch_add(*val, Ordering::Relaxed); }).unwrap();
+        assert_eq!(total.into_inner(), 1055);
+
+        let broken = tp.par_for_each(&values, |val| assert!(*val < 105, "too big"));
+        assert_eq!(broken.unwrap_err().message, "too big");
+    }
+
+    #[test]
+    fn matrix_rows() {
+        let tp: ThreadPool = ThreadPool::new(2);
+        let matrix = Matrix::new("#..##.#..".chars().collect(), 3);
+
+        let walls = tp.par_rows(&matrix, |row, cells| (row, cells.iter().filter(|c| **c == '#').count()));
+
+        assert_eq!(walls, Ok(vec![(0, 1), (1, 2), (2, 1)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk_size of at least 1")]
+    fn empty_chunks() {
+        let tp: ThreadPool = ThreadPool::new(1);
+        let _ = tp.par_chunks(&[1, 2, 3], 0, |_, chunk| chunk.len());
+    }
+
+    #[test]
+    fn results_outlive_their_jobs() {
+        let tp: ThreadPool = ThreadPool::new(2);
+
+        // A job that never runs still lets its waiter go
+        let (handle, promise) = JobHandle::<i32>::new(&tp.shared);
+        assert_eq!(handle.join_timeout(Duration::from_millis(10)), None);
+        drop(promise);
+        assert_eq!(handle.join(), Err(JobPanic { message: "job was lost".to_string() }));
+
+        // Nobody waits on these, the jobs are the last ones holding their results
+        let text = String::from("borrowed");
+        tp.scope(|scope| {
+            for _ in 0..8 {
+                drop(scope.spawn(|| text.as_str()));
+            }
+        });
+    }
+
+    /// CPU time the calling thread used so far, in clock ticks.
+    #[cfg(target_os = "linux")]
+    fn thread_cpu_ticks() -> u64 {
+        let stat = std::fs::read_to_string("/proc/thread-self/stat").unwrap();
+        // The command name is in parentheses and may hold spaces, so count from after it.
+        // utime and stime are the 12th and 13th fields from there.
+        let fields: Vec<&str> = stat[stat.rfind(')').unwrap() + 2..].split(' ').collect();
+        fields[11].parse::<u64>().unwrap() + fields[12].parse::<u64>().unwrap()
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn waiting_workers_sleep() {
+        let tp: ThreadPool = ThreadPool::new(2);
+        let (started, wait_for_start) = mpsc::channel();
+
+        let spent = tp.scope(|scope| scope.spawn(move || {
+            let before = thread_cpu_ticks();
+            join(
+                // Only waits once the other worker took `b`
+                move || wait_for_start.recv().unwrap(),
+                move || {
+                    started.send(()).unwrap();
+                    thread::sleep(Duration::from_millis(300));
+                },
+            );
+            thread_cpu_ticks() - before
+        }).join()).unwrap();
+
+        // Spinning through the wait would be about 30 ticks
+        assert!(spent < 10, "waiting used {spent} ticks of CPU");
+    }
+
+    fn parallel_sum(values: &[u64]) -> u64 {
+        if values.len() <= 4 {
+            return values.iter().sum();
+        }
+
+        let (left, right) = values.split_at(values.len() / 2);
+        let (left, right) = join(|| parallel_sum(left), || parallel_sum(right));
+        left + right
+    }
+
+    #[test]
+    fn nested_jobs() {
+        let values: Vec<u64> = (1..=1000).collect();
+
+        // Outside a pool it's just recursion
+        assert_eq!(parallel_sum(&values), 500_500);
+
+        // A single worker has to run the sub-jobs while it waits on them
+        for size in [1, 4] {
+            let tp: ThreadPool = ThreadPool::new(size);
+            let sum = tp.scope(|scope| scope.spawn(|| parallel_sum(&values)).join());
+            assert_eq!(sum, Ok(500_500));
+
+            let leaves = AtomicUsize::new(0);
+            tp.scope(|scope| {
+                for _ in 0..4 {
+                    scope.spawn(|| {
+                        for _ in 0..4 {
+                            scope.spawn(|| leaves.fetch_add(1, Ordering::Relaxed));
+                        }
+                    });
+                }
+            });
+            assert_eq!(leaves.into_inner(), 16);
+        }
+
+        let tp: ThreadPool = ThreadPool::new(2);
+        let broken = tp.scope(|scope| scope.spawn(|| join(|| 1, || -> i32 { panic!("right half") })).join());
+        assert_eq!(broken.unwrap_err().message, "right half");
+    }
+
+    /// The design before work stealing: every worker waits on one shared receiver.
+    struct SharedReceiverPool {
+        sender: Option<mpsc::Sender<Box<dyn FnOnce() + Send>>>,
+        workers: Vec<thread::JoinHandle<()>>,
+    }
+
+    impl SharedReceiverPool {
+        fn new(size: usize) -> Self {
+            let (sender, receiver) = mpsc::channel::<Box<dyn FnOnce() + Send>>();
+            let receiver = Arc::new(Mutex::new(receiver));
+
+            let workers = (0..size)
+                .map(|_| {
+                    let receiver = Arc::clone(&receiver);
+                    thread::spawn(move || loop {
+                        let message = receiver.lock().unwrap().recv();
+                        match message {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    })
+                })
+                .collect();
+
+            Self { sender: Some(sender), workers }
+        }
+
+        fn execute(&self, job: impl FnOnce() + Send + 'static) {
+            self.sender.as_ref().unwrap().send(Box::new(job)).unwrap();
+        }
+    }
+
+    impl Drop for SharedReceiverPool {
+        fn drop(&mut self) {
+            drop(self.sender.take());
+            for worker in self.workers.drain(..) {
+                worker.join().unwrap();
+            }
+        }
+    }
+
+    /// Lots of tiny jobs, like one per equation in day 7 or per design in day 19.
+    /// Run with `cargo test --release -- --ignored --nocapture bench`.
+    #[test]
+    #[ignore]
+    fn bench_tiny_jobs() {
+        const JOBS: usize = 200_000;
+        let size = thread::available_parallelism().map_or(4, |count| count.get());
+
+        let tiny_job = |counter: Arc<AtomicUsize>| move || {
+            counter.fetch_add(std::hint::black_box(1), Ordering::Relaxed);
+        };
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let start = Instant::now();
+        {
+            let pool = SharedReceiverPool::new(size);
+            for _ in 0..JOBS {
+                pool.execute(tiny_job(Arc::clone(&counter)));
+            }
+        }
+        let shared_receiver = start.elapsed();
+        assert_eq!(counter.load(Ordering::Relaxed), JOBS);
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let start = Instant::now();
+        {
+            let pool: ThreadPool = ThreadPool::new(size);
+            for _ in 0..JOBS {
+                let job = tiny_job(Arc::clone(&counter));
+                pool.send(Box::new(move |_, _| job()));
+            }
+        }
+        let stealing = start.elapsed();
+        assert_eq!(counter.load(Ordering::Relaxed), JOBS);
+
+        let counter = AtomicUsize::new(0);
+        let items = vec![(); JOBS];
+        let start = Instant::now();
+        {
+            let pool: ThreadPool = ThreadPool::new(size);
+            pool.par_for_each(&items, |_| { counter.fetch_add(std::hint::black_box(1), Ordering::Relaxed); }).unwrap();
+        }
+        let chunked = start.elapsed();
+        assert_eq!(counter.into_inner(), JOBS);
+
+        println!("{JOBS} tiny jobs on {size} workers:");
+        println!("  shared receiver:  {shared_receiver:?}");
+        println!("  work stealing:    {stealing:?}");
+        println!("  par_for_each:     {chunked:?}");
+    }
+
+    /// Divide and conquer can only be done with nested jobs on the new pool,
+    /// the old one can just get a job per leaf.
+    #[test]
+    #[ignore]
+    fn bench_divide_and_conquer() {
+        let values: Vec<u64> = (0..2_000_000).collect();
+        let expected: u64 = values.iter().sum();
+        let size = thread::available_parallelism().map_or(4, |count| count.get());
+
+        let values = Arc::new(values);
+        let start = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+        {
+            let pool = SharedReceiverPool::new(size);
+            for leaf in 0..values.len().div_ceil(4) {
+                let (values, sender) = (Arc::clone(&values), sender.clone());
+                pool.execute(move || {
+                    let end = (leaf * 4 + 4).min(values.len());
+                    sender.send(values[leaf * 4..end].iter().sum::<u64>()).unwrap();
+                });
+            }
+        }
+        drop(sender);
+        assert_eq!(receiver.iter().sum::<u64>(), expected);
+        let shared_receiver = start.elapsed();
+
+        let start = Instant::now();
+        {
+            let pool: ThreadPool = ThreadPool::new(size);
+            let sum = pool.scope(|scope| scope.spawn(|| parallel_sum(&values)).join());
+            assert_eq!(sum, Ok(expected));
+        }
+        let stealing = start.elapsed();
+
+        println!("sum of {} values on {size} workers:", values.len());
+        println!("  shared receiver, a job per leaf: {shared_receiver:?}");
+        println!("  work stealing with join:         {stealing:?}");
+    }
+
+    #[test]
+    fn panicking_logger() {
+        let (done, finished) = mpsc::channel();
+
+        thread::spawn(move || {
+            let started = AtomicUsize::new(0);
+            let tp: ThreadPool = ThreadPool::with_logger(2, move |event| {
+                if matches!(event, Event::JobStarted { .. }) && started.fetch_add(1, Ordering::SeqCst) % 2 == 1 {
+                    panic!("bad logger");
+                }
+            });
+
+            let values = [1, 2, 3, 4];
+            let _ = done.send(tp.par_chunks(&values, 1, |_, chunk| chunk[0] * 2));
+        });
+
+        let doubled = finished.recv_timeout(Duration::from_secs(5)).expect("scope hung");
+        assert_eq!(doubled, Ok(vec![2, 4, 6, 8]));
+    }
+
+    #[test]
+    fn waiting_outlives_unwinding_jobs() {
+        let (done, finished) = mpsc::channel();
+
+        thread::spawn(move || {
+            let tp: ThreadPool = ThreadPool::new(2);
+            let (stolen, wait_for_steal) = mpsc::channel();
+            let (queued, wait_for_queue) = mpsc::channel();
+            let borrowed = vec![1, 2, 3];
+            let borrowed = &borrowed;
+
+            let result = tp.scope(|scope| {
+                let handle = scope.spawn(move || {
+                    join(
+                        move || {
+                            // Once the other worker has `b`, only the killer is left for us to help with
+                            wait_for_steal.recv().unwrap();
+                            queued.send(()).unwrap();
+                        },
+                        move || {
+                            stolen.send(()).unwrap();
+                            thread::sleep(Duration::from_millis(200));
+                            borrowed.iter().sum::<i32>()
+                        },
+                    )
+                });
+
+                wait_for_queue.recv().unwrap();
+                // Not wrapped in `catch`, so it unwinds through `join`
+                tp.send(Box::new(|_, _| panic!("worker killer")));
+
+                handle.join()
+            });
+
+            let _ = done.send(result.map(|_| ()).unwrap_err().message);
+        });
+
+        let message = finished.recv_timeout(Duration::from_secs(5)).expect("join hung");
+        assert_eq!(message, "worker killer");
+    }
 }
@@ -0,0 +1,383 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    process::{Command as Shell, Stdio},
+    sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc},
+    thread,
+    time::Duration,
+};
+use crate::matrix::Matrix;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turned on by the `--visualize` flag.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(value: bool) {
+    ENABLED.store(value, Ordering::Relaxed);
+}
+
+const DEFAULT_DELAY: Duration = Duration::from_millis(32);
+const MIN_DELAY: Duration = Duration::from_millis(1);
+const MAX_DELAY: Duration = Duration::from_secs(2);
+/// Computed frames kept around for stepping back.
+const DEFAULT_HISTORY: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    TogglePause,
+    Step,
+    Back,
+    Faster,
+    Slower,
+    Quit,
+}
+
+impl Command {
+    pub fn from_key(key: char) -> Option<Self> {
+        match key {
+            ' ' | 'p' => Some(Command::TogglePause),
+            'n' | 'l' => Some(Command::Step),
+            'b' | 'h' => Some(Command::Back),
+            '+' | 'k' => Some(Command::Faster),
+            '-' | 'j' => Some(Command::Slower),
+            'q' => Some(Command::Quit),
+            _ => None,
+        }
+    }
+}
+
+type StepFn<'a> = Box<dyn FnMut(&Matrix<char>) -> Option<Matrix<char>> + 'a>;
+type RenderFn<'a> = Box<dyn Fn(&Matrix<char>) -> String + 'a>;
+
+/// Plays a sequence of `Matrix<char>` states.
+///
+/// States coming from a step closure are only computed when we get to them,
+/// and the last few are kept around so we can step back.
+pub struct Player<'a> {
+    frames: VecDeque<Matrix<char>>,
+    /// Frame number of `frames[0]`, once old ones start getting dropped
+    first: usize,
+    history: usize,
+    step: Option<StepFn<'a>>,
+    render: Option<RenderFn<'a>>,
+    index: usize,
+    playing: bool,
+    delay: Duration,
+}
+
+impl<'a> Player<'a> {
+    /// # Panics
+    ///
+    /// Panics if there are no frames.
+    pub fn new(frames: Vec<Matrix<char>>) -> Self {
+        assert!(!frames.is_empty());
+
+        Self {
+            frames: frames.into(),
+            first: 0,
+            history: DEFAULT_HISTORY,
+            step: None,
+            render: None,
+            index: 0,
+            playing: true,
+            delay: DEFAULT_DELAY,
+        }
+    }
+
+    /// `step` gets the current state and returns the next one,
+    /// or None when the simulation is over.
+    pub fn from_fn(
+        initial: Matrix<char>,
+        step: impl FnMut(&Matrix<char>) -> Option<Matrix<char>> + 'a,
+    ) -> Self {
+        let mut player = Self::new(vec![initial]);
+        player.step = Some(Box::new(step));
+        player
+    }
+
+    /// Draw the frames with something other than their Display,
+    /// like a `render::Renderer`.
+    pub fn with_renderer(mut self, render: impl Fn(&Matrix<char>) -> String + 'a) -> Self {
+        self.render = Some(Box::new(render));
+        self
+    }
+
+    /// Keep at most `history` computed frames to step back through.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `history` is zero.
+    pub fn with_history(mut self, history: usize) -> Self {
+        assert!(history > 0);
+
+        self.history = history;
+        self
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub fn current(&self) -> &Matrix<char> {
+        &self.frames[self.index - self.first]
+    }
+
+    /// Move to the next frame, computing it if needed.
+    /// Returns false if we were already at the last one.
+    fn advance(&mut self) -> bool {
+        if self.index + 1 == self.first + self.frames.len() {
+            let next = match &mut self.step {
+                Some(step) => step(&self.frames[self.index - self.first]),
+                None => None,
+            };
+
+            match next {
+                Some(frame) => {
+                    self.frames.push_back(frame);
+                    if self.frames.len() > self.history {
+                        self.frames.pop_front();
+                        self.first += 1;
+                    }
+                },
+                None => {
+                    self.step = None;
+                    return false;
+                }
+            }
+        }
+
+        self.index += 1;
+        true
+    }
+
+    /// Returns false once we should stop.
+    pub fn apply(&mut self, command: Command) -> bool {
+        match command {
+            Command::TogglePause => self.playing = !self.playing,
+            Command::Step => {
+                self.playing = false;
+                self.advance();
+            },
+            Command::Back => {
+                self.playing = false;
+                self.index = self.index.saturating_sub(1).max(self.first);
+            },
+            Command::Faster => self.delay = (self.delay / 2).max(MIN_DELAY),
+            Command::Slower => self.delay = (self.delay * 2).min(MAX_DELAY),
+            Command::Quit => return false,
+        }
+
+        true
+    }
+
+    /// What happens when no key was pressed during a frame.
+    pub fn tick(&mut self) {
+        if self.playing && !self.advance() {
+            self.playing = false;
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let state = if self.playing { "playing" } else { "paused" };
+
+        write!(out, "{esc}[2J{esc}[1;1H", esc = 27 as char)?;
+        match &self.render {
+            Some(render) => write!(out, "{}", render(self.current()))?,
+            None => write!(out, "{}", self.current())?,
+        }
+        writeln!(out, "frame {} [{state}] {:?}", self.index, self.delay)?;
+        writeln!(out, "space: play/pause  n: step  b: back  +/-: speed  q: quit")?;
+        out.flush()
+    }
+
+    /// Take over the terminal until the user quits.
+    pub fn run(&mut self) -> io::Result<()> {
+        // Dropped in reverse, so the reader stops before the terminal goes back to normal
+        let _raw = RawMode::enable()?;
+        let keys = KeyReader::spawn();
+
+        let mut stdout = io::stdout();
+        loop {
+            self.draw(&mut stdout)?;
+
+            match keys.receiver.recv_timeout(self.delay) {
+                Ok(key) => {
+                    if let Some(command) = Command::from_key(key) {
+                        if !self.apply(command) {
+                            break;
+                        }
+                    }
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => self.tick(),
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads keys on its own thread. It's stopped and joined when dropped,
+/// so whatever reads stdin next doesn't lose keys to it.
+struct KeyReader {
+    receiver: mpsc::Receiver<char>,
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl KeyReader {
+    fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+
+        let thread = thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut buffer = [0; 16];
+
+            while !stopped.load(Ordering::Relaxed) {
+                // `RawMode` makes this give up after a tenth of a second
+                match stdin.read(&mut buffer) {
+                    Ok(0) => thread::sleep(Duration::from_millis(10)),
+                    Ok(read) => {
+                        for byte in &buffer[..read] {
+                            if sender.send(*byte as char).is_err() {
+                                return;
+                            }
+                        }
+                    },
+                    Err(_) => return,
+                }
+            }
+        });
+
+        Self { receiver, stop, thread: Some(thread) }
+    }
+}
+
+impl Drop for KeyReader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Keys get to us without waiting for enter and without being echoed.
+/// Reads wait a tenth of a second at most. Goes back to normal when dropped.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        stty(&["-icanon", "-echo", "min", "0", "time", "1"])?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if let Err(e) = stty(&["icanon", "echo"]) {
+            eprintln!("Couldn't restore the terminal ({e}), `stty sane` should fix it");
+        }
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<()> {
+    let status = Shell::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("stty {} failed with {status}", args.join(" "))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter(n: usize) -> Matrix<char> {
+        Matrix::new(vec![char::from_digit(n as u32, 10).unwrap()], 1)
+    }
+
+    #[test]
+    fn test_step_and_back() {
+        let mut player = Player::new(vec![counter(0), counter(1), counter(2)]);
+
+        player.apply(Command::Step);
+        player.apply(Command::Step);
+        player.apply(Command::Step);
+        assert_eq!(player.index(), 2);
+        assert!(!player.playing());
+
+        player.apply(Command::Back);
+        assert_eq!(player.current()[crate::position::UPos::new(0,0)], '1');
+    }
+
+    #[test]
+    fn test_lazy_steps() {
+        let mut player = Player::from_fn(counter(0), |frame| {
+            let n = frame.iter().next()?.to_digit(10)? as usize;
+            (n < 3).then(|| counter(n + 1))
+        });
+
+        for _ in 0..10 {
+            player.tick();
+        }
+
+        assert_eq!(player.index(), 3);
+        assert!(!player.playing());
+    }
+
+    #[test]
+    fn test_speed() {
+        let mut player = Player::new(vec![counter(0)]);
+
+        player.apply(Command::Faster);
+        assert_eq!(player.delay(), DEFAULT_DELAY / 2);
+
+        for _ in 0..20 {
+            player.apply(Command::Slower);
+        }
+        assert_eq!(player.delay(), MAX_DELAY);
+
+        assert!(!player.apply(Command::Quit));
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut player = Player::from_fn(counter(0), |frame| {
+            let n = frame.iter().next()?.to_digit(10)? as usize;
+            (n < 9).then(|| counter(n + 1))
+        })
+        .with_history(3);
+
+        for _ in 0..6 {
+            player.apply(Command::Step);
+        }
+        assert_eq!(player.index(), 6);
+        assert_eq!(player.frames.len(), 3);
+
+        for _ in 0..5 {
+            player.apply(Command::Back);
+        }
+        assert_eq!(player.index(), 4);
+        assert_eq!(player.current()[crate::position::UPos::new(0,0)], '4');
+    }
+}